@@ -2,10 +2,15 @@
 //!
 //! Uma carteira de linha de comando para interagir com a blockchain CivicChain.
 //! Permite enviar transações, minerar blocos, consultar o estado da blockchain,
-//! implantar e chamar contratos inteligentes.
+//! implantar e chamar contratos inteligentes, e verificar a cadeia como cliente
+//! leve com provas FlyClient.
 
+use civicchain_primitives::flyclient::{self, FlyClientProof};
+use civicchain_runtime::Header;
 use clap::{Parser, Subcommand};
-use sp_core::{crypto::Ss58Codec, sr25519, Pair, H256, U256};
+use jsonrpsee::{core::client::ClientT, rpc_params, ws_client::WsClientBuilder};
+use parity_scale_codec::Decode;
+use sp_core::{crypto::Ss58Codec, sr25519, Bytes, Pair, H256, U256};
 use sp_keyring::AccountKeyring;
 use std::{fs, path::PathBuf};
 use substrate_api_client::{
@@ -105,6 +110,17 @@ enum Commands {
         #[arg(short, long)]
         output: PathBuf,
     },
+
+    /// Verificar a cadeia do nó com uma prova FlyClient, sem baixar todos os cabeçalhos
+    VerifyChain {
+        /// Número de blocos amostrados pela dificuldade
+        #[arg(short, long, default_value = "64")]
+        security_param: u32,
+
+        /// Hash do bloco gênese esperado (por padrão, o informado pelo nó)
+        #[arg(short, long)]
+        genesis: Option<H256>,
+    },
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -112,6 +128,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Conectar ao nó
     println!("Conectando ao nó CivicChain em {}...", cli.url);
+
+    // A verificação FlyClient não precisa de chave nem da API completa
+    if let Commands::VerifyChain { security_param, genesis } = cli.command {
+        return verify_chain(&cli.url, security_param, genesis);
+    }
+
     let client = WsRpcClient::new(&cli.url)?;
     
     // Obter o par de chaves
@@ -186,7 +208,40 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             fs::write(output, seed)?;
             println!("Chave gerada com sucesso. Endereço: {}", new_pair.public().to_ss58check());
         }
+        Commands::VerifyChain { .. } => unreachable!("Tratado antes de carregar a chave"),
     }
 
     Ok(())
 }
+
+/// Pede ao nó uma prova FlyClient do bloco mais recente e a verifica localmente.
+///
+/// Só os cabeçalhos amostrados são baixados, e cada um tem o selo Yespower
+/// conferido, então a dificuldade total mostrada não depende de confiar no nó.
+fn verify_chain(
+    url: &str,
+    security_param: u32,
+    genesis: Option<H256>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let (genesis, proof) = runtime.block_on(async {
+        let client = WsClientBuilder::default().build(url).await?;
+        let genesis = match genesis {
+            Some(genesis) => genesis,
+            None => client.request("chain_getBlockHash", rpc_params![0]).await?,
+        };
+        let proof: Bytes = client
+            .request("flyclient_getProof", rpc_params![None::<H256>, security_param])
+            .await?;
+        Ok::<_, jsonrpsee::core::Error>((genesis, proof))
+    })?;
+
+    let proof = FlyClientProof::<Header>::decode(&mut &proof[..])?;
+    let head = flyclient::verify(&proof, genesis, security_param)
+        .map_err(|err| format!("Prova FlyClient inválida: {:?}", err))?;
+
+    println!("Cadeia verificada com {} amostras", proof.samples.len());
+    println!("Bloco mais recente: #{} ({:?})", head.number, head.hash);
+    println!("Dificuldade total: {}", head.total_difficulty);
+    Ok(())
+}
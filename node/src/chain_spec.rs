@@ -1,3 +1,4 @@
+use crate::checkpoint::Checkpoint;
 use civicchain_runtime::{
     AccountId, BalancesConfig, ContractsPalletConfig, GenesisConfig, PowPalletConfig, Signature,
     SudoConfig, SystemConfig, WASM_BINARY,
@@ -20,21 +21,23 @@ pub fn get_from_seed<TPublic: Public>(seed: &str) -> <TPublic::Pair as Pair>::Pu
 }
 
 /// The extensions for the [`ChainSpec`].
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ChainSpecExtension)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ChainSpecExtension)]
 #[serde(deny_unknown_fields)]
 pub struct Extensions {
-    /// The relay chain of the Parachain.
-    pub relay_chain: String,
-    /// The id of the Parachain.
-    pub para_id: u32,
+    /// Blocks of the canonical chain every node must follow.
+    #[serde(default)]
+    pub checkpoints: Vec<Checkpoint>,
+    /// Total difficulty a chain needs before it can become the best chain.
+    #[serde(default)]
+    pub min_total_difficulty: U256,
 }
 
 impl Extensions {
     /// Create a new instance of [`Extensions`].
-    pub fn new(relay_chain: String, para_id: u32) -> Self {
+    pub fn new(checkpoints: Vec<Checkpoint>, min_total_difficulty: U256) -> Self {
         Self {
-            relay_chain,
-            para_id,
+            checkpoints,
+            min_total_difficulty,
         }
     }
 }
@@ -99,11 +102,8 @@ pub fn development_config() -> Result<ChainSpec, String> {
             )
             .expect("Provided valid json map"),
         ),
-        // Extensions
-        Extensions {
-            relay_chain: "rococo-local".into(),
-            para_id: 2000,
-        },
+        // Extensions: a new chain has no checkpoints yet
+        Extensions::default(),
     ))
 }
 
@@ -163,11 +163,8 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
             )
             .expect("Provided valid json map"),
         ),
-        // Extensions
-        Extensions {
-            relay_chain: "rococo-local".into(),
-            para_id: 2000,
-        },
+        // Extensions: a new chain has no checkpoints yet
+        Extensions::default(),
     ))
 }
 
@@ -0,0 +1,151 @@
+//! Checkpoints and minimum chain work.
+//!
+//! The chain spec of a public network lists checkpoints, blocks of its
+//! canonical chain every node must follow, and the total difficulty that chain
+//! had reached when the spec was released. `CheckpointBlockImport` rejects
+//! blocks that contradict a checkpoint, and forks branching off below the last
+//! checkpoint once that checkpoint is imported, so a fresh node cannot be fed
+//! an alternate history.
+//!
+//! Every chain starts out below the minimum total difficulty, so blocks below
+//! it are still imported, but they never become the best block: a long chain
+//! of cheap blocks is never followed, mined on or reported as the chain head.
+
+use crate::ghost::seal_difficulty;
+use sc_client_api::AuxStore;
+use sc_consensus::{
+    BlockCheckParams, BlockImport, BlockImportParams, ForkChoiceStrategy, ImportResult,
+};
+use sc_consensus_pow::PowAux;
+use serde::{Deserialize, Serialize};
+use sp_blockchain::HeaderBackend;
+use sp_consensus::Error as ConsensusError;
+use sp_core::{H256, U256};
+use sp_runtime::{
+    traits::{Block as BlockT, Header as HeaderT},
+    SaturatedConversion,
+};
+use std::sync::Arc;
+
+#[cfg(test)]
+mod tests;
+
+/// Block of the canonical chain, as listed in the chain spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Number of the block.
+    pub number: u64,
+    /// Hash of the block.
+    pub hash: H256,
+}
+
+/// Block import wrapper enforcing checkpoints and the minimum chain work.
+pub struct CheckpointBlockImport<I, C> {
+    inner: I,
+    client: Arc<C>,
+    checkpoints: Arc<Vec<Checkpoint>>,
+    min_total_difficulty: U256,
+}
+
+impl<I, C> CheckpointBlockImport<I, C> {
+    /// Wrap `inner`, which only sees blocks agreeing with `checkpoints`.
+    pub fn new(
+        inner: I,
+        client: Arc<C>,
+        checkpoints: Vec<Checkpoint>,
+        min_total_difficulty: U256,
+    ) -> Self {
+        Self {
+            inner,
+            client,
+            checkpoints: Arc::new(checkpoints),
+            min_total_difficulty,
+        }
+    }
+}
+
+// Derive would require `C: Clone`.
+impl<I: Clone, C> Clone for CheckpointBlockImport<I, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            client: self.client.clone(),
+            checkpoints: self.checkpoints.clone(),
+            min_total_difficulty: self.min_total_difficulty,
+        }
+    }
+}
+
+impl<I, C> CheckpointBlockImport<I, C> {
+    /// Check `hash`, at height `number`, against the checkpoints.
+    fn check_checkpoints<B>(&self, number: u64, hash: H256) -> Result<(), ConsensusError>
+    where
+        B: BlockT<Hash = H256>,
+        C: HeaderBackend<B>,
+    {
+        if let Some(checkpoint) = self.checkpoints.iter().find(|c| c.number == number) {
+            if checkpoint.hash != hash {
+                return Err(ConsensusError::ClientImport(format!(
+                    "Block #{} ({:?}) does not match checkpoint {:?}",
+                    number, hash, checkpoint.hash
+                )));
+            }
+        }
+
+        // Once the last checkpoint is imported every block up to it is known, so a
+        // new block at or below it can only start a fork
+        if let Some(last) = self.checkpoints.iter().max_by_key(|c| c.number) {
+            let canonical = self
+                .client
+                .hash(last.number.saturated_into())
+                .map_err(|err| ConsensusError::ChainLookup(err.to_string()))?;
+            if number <= last.number && canonical == Some(last.hash) {
+                return Err(ConsensusError::ClientImport(format!(
+                    "Block #{} forks off below checkpoint #{}",
+                    number, last.number
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl<B, I, C> BlockImport<B> for CheckpointBlockImport<I, C>
+where
+    B: BlockT<Hash = H256>,
+    I: BlockImport<B, Error = ConsensusError> + Send,
+    C: HeaderBackend<B> + AuxStore + Send + Sync,
+{
+    type Error = ConsensusError;
+    type Transaction = I::Transaction;
+
+    async fn check_block(
+        &mut self,
+        block: BlockCheckParams<B>,
+    ) -> Result<ImportResult, Self::Error> {
+        self.inner.check_block(block).await
+    }
+
+    async fn import_block(
+        &mut self,
+        mut block: BlockImportParams<B, Self::Transaction>,
+    ) -> Result<ImportResult, Self::Error> {
+        let number: u64 = (*block.header.number()).saturated_into();
+        self.check_checkpoints(number, block.post_hash())?;
+
+        // Blocks without a valid seal are rejected by the inner import anyway.
+        if let Some(difficulty) = seal_difficulty(&block.post_digests) {
+            let parent = PowAux::<U256>::read::<_, B>(&*self.client, block.header.parent_hash())
+                .map_err(|err| ConsensusError::ChainLookup(err.to_string()))?;
+            let total_difficulty = parent.total_difficulty.saturating_add(difficulty);
+
+            if total_difficulty < self.min_total_difficulty {
+                block.fork_choice = Some(ForkChoiceStrategy::Custom(false));
+            }
+        }
+
+        self.inner.import_block(block).await
+    }
+}
@@ -0,0 +1,127 @@
+use super::*;
+use crate::test_utils::{self, TestImport};
+use civicchain_runtime::opaque::Block;
+use sc_client_api::in_mem;
+
+type TestBlockchain = in_mem::Blockchain<Block>;
+
+// Checkpoint import over an in-memory chain, starting at a genesis block.
+struct TestChain {
+    blockchain: TestBlockchain,
+    import: CheckpointBlockImport<TestImport, TestBlockchain>,
+    genesis: H256,
+}
+
+impl TestChain {
+    fn new(checkpoints: Vec<Checkpoint>, min_total_difficulty: u64) -> Self {
+        let blockchain = TestBlockchain::new();
+        let genesis = test_utils::genesis(&blockchain);
+        let import = CheckpointBlockImport::new(
+            TestImport {
+                blockchain: blockchain.clone(),
+            },
+            Arc::new(blockchain.clone()),
+            checkpoints,
+            min_total_difficulty.into(),
+        );
+        Self {
+            blockchain,
+            import,
+            genesis,
+        }
+    }
+
+    // Hash a block on top of `parent` would have.
+    fn hash(&self, parent: H256, salt: u8) -> H256 {
+        test_utils::block(&self.blockchain, parent, salt, 10).post_hash()
+    }
+
+    // Import a block of difficulty 10 on top of `parent`.
+    async fn import(&mut self, parent: H256, salt: u8) -> Result<H256, ConsensusError> {
+        self.import_with_difficulty(parent, salt, 10).await
+    }
+
+    async fn import_with_difficulty(
+        &mut self,
+        parent: H256,
+        salt: u8,
+        difficulty: u64,
+    ) -> Result<H256, ConsensusError> {
+        let block = test_utils::block(&self.blockchain, parent, salt, difficulty);
+        let hash = block.post_hash();
+        self.import.import_block(block).await?;
+        Ok(hash)
+    }
+
+    fn best(&self) -> H256 {
+        self.blockchain.info().best_hash
+    }
+}
+
+#[tokio::test]
+async fn blocks_must_match_checkpoints() {
+    let mut chain = TestChain::new(Vec::new(), 0);
+    let a1 = chain.hash(chain.genesis, 1);
+    chain = TestChain::new(
+        vec![Checkpoint {
+            number: 1,
+            hash: a1,
+        }],
+        0,
+    );
+
+    // Another block at the checkpoint height is rejected
+    assert!(chain.import(chain.genesis, 2).await.is_err());
+    assert_eq!(chain.import(chain.genesis, 1).await.unwrap(), a1);
+    assert_eq!(chain.best(), a1);
+}
+
+#[tokio::test]
+async fn forks_below_the_last_checkpoint_are_rejected() {
+    let mut chain = TestChain::new(Vec::new(), 0);
+    let a1 = chain.hash(chain.genesis, 1);
+    let a2 = chain.hash(a1, 1);
+    chain = TestChain::new(
+        vec![Checkpoint {
+            number: 2,
+            hash: a2,
+        }],
+        0,
+    );
+
+    // Before the checkpoint is imported, forks off earlier blocks are allowed
+    chain.import(chain.genesis, 1).await.unwrap();
+    let b1 = chain.import(chain.genesis, 2).await.unwrap();
+
+    chain.import(a1, 1).await.unwrap();
+    assert_eq!(chain.best(), a2);
+    assert!(chain.import(chain.genesis, 3).await.is_err());
+    assert!(chain.import(b1, 2).await.is_err());
+
+    // Blocks on top of the checkpoint are not forks of it
+    chain.import(a2, 1).await.unwrap();
+    chain.import(a2, 2).await.unwrap();
+}
+
+#[tokio::test]
+async fn chains_below_min_total_difficulty_never_become_best() {
+    let mut chain = TestChain::new(Vec::new(), 100);
+
+    // Nine blocks add up to 90, short of the minimum
+    let mut tip = chain.genesis;
+    for _ in 0..9 {
+        tip = chain.import(tip, 1).await.unwrap();
+        assert_eq!(chain.best(), chain.genesis);
+    }
+
+    // A heavier fork reaching the minimum becomes best, even though it is shorter
+    let b1 = chain
+        .import_with_difficulty(chain.genesis, 2, 100)
+        .await
+        .unwrap();
+    assert_eq!(chain.best(), b1);
+
+    // As does the first chain, once it reaches the minimum and outgrows the fork
+    let tip = chain.import(tip, 1).await.unwrap();
+    assert_eq!(chain.best(), tip);
+}
@@ -1,5 +1,8 @@
+use crate::{ghost::ForkChoice, reorg::DEFAULT_MAX_REORG_DEPTH};
+use civicchain_runtime::AccountId;
 use clap::Parser;
 use sc_cli::RunCmd;
+use sp_core::crypto::Ss58Codec;
 
 #[derive(Debug, Parser)]
 pub struct Cli {
@@ -8,6 +11,59 @@ pub struct Cli {
 
     #[clap(flatten)]
     pub run: RunCmd,
+
+    /// Rule used to pick the best block among competing forks.
+    #[arg(long, value_enum, default_value_t = ForkChoice::Ghost)]
+    pub fork_choice: ForkChoice,
+
+    /// Most blocks of the best chain a fork may retract. Deeper forks are refused.
+    #[arg(long, default_value_t = DEFAULT_MAX_REORG_DEPTH)]
+    pub max_reorg_depth: u32,
+
+    #[clap(flatten)]
+    pub mining: MiningParams,
+}
+
+/// Options for the built-in Yespower-R16 miner.
+#[derive(Debug, Clone, clap::Args)]
+pub struct MiningParams {
+    /// Mine blocks with the built-in CPU miner.
+    #[arg(long, requires = "mining_author")]
+    pub mine: bool,
+
+    /// Number of hashing threads used by the built-in miner.
+    #[arg(long, default_value_t = 1)]
+    pub mining_threads: usize,
+
+    /// Account (SS58) credited as the author of mined blocks.
+    #[arg(long, value_parser = parse_account_id)]
+    pub mining_author: Option<AccountId>,
+
+    /// Listen address of the Stratum V1 server for external miners.
+    #[cfg(feature = "stratum")]
+    #[arg(long)]
+    pub stratum_listen: Option<std::net::SocketAddr>,
+
+    /// Difficulty of the shares accepted by the Stratum server.
+    #[cfg(feature = "stratum")]
+    #[arg(long, default_value_t = 1_000)]
+    pub stratum_share_difficulty: u64,
+}
+
+impl MiningParams {
+    /// Whether this node builds blocks for its own miner or external ones.
+    pub fn is_mining(&self) -> bool {
+        #[cfg(feature = "stratum")]
+        if self.stratum_listen.is_some() {
+            return true;
+        }
+
+        self.mine
+    }
+}
+
+fn parse_account_id(address: &str) -> Result<AccountId, String> {
+    AccountId::from_ss58check(address).map_err(|err| format!("Invalid SS58 address: {:?}", err))
 }
 
 #[derive(Debug, Parser)]
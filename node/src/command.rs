@@ -66,7 +66,7 @@ pub fn run() -> sc_cli::Result<()> {
                     task_manager,
                     import_queue,
                     ..
-                } = service::new_partial(&config)?;
+                } = service::new_partial(&config, cli.fork_choice, cli.max_reorg_depth)?;
                 Ok((cmd.run(client, import_queue), task_manager))
             })
         }
@@ -77,7 +77,7 @@ pub fn run() -> sc_cli::Result<()> {
                     client,
                     task_manager,
                     ..
-                } = service::new_partial(&config)?;
+                } = service::new_partial(&config, cli.fork_choice, cli.max_reorg_depth)?;
                 Ok((cmd.run(client, config.database), task_manager))
             })
         }
@@ -88,7 +88,7 @@ pub fn run() -> sc_cli::Result<()> {
                     client,
                     task_manager,
                     ..
-                } = service::new_partial(&config)?;
+                } = service::new_partial(&config, cli.fork_choice, cli.max_reorg_depth)?;
                 Ok((cmd.run(client, config.chain_spec), task_manager))
             })
         }
@@ -100,7 +100,7 @@ pub fn run() -> sc_cli::Result<()> {
                     task_manager,
                     import_queue,
                     ..
-                } = service::new_partial(&config)?;
+                } = service::new_partial(&config, cli.fork_choice, cli.max_reorg_depth)?;
                 Ok((cmd.run(client, import_queue), task_manager))
             })
         }
@@ -116,7 +116,7 @@ pub fn run() -> sc_cli::Result<()> {
                     task_manager,
                     backend,
                     ..
-                } = service::new_partial(&config)?;
+                } = service::new_partial(&config, cli.fork_choice, cli.max_reorg_depth)?;
                 let aux_revert = Box::new(|client, _, blocks| {
                     sc_finality_grandpa::revert(client, blocks)?;
                     Ok(())
@@ -143,20 +143,20 @@ pub fn run() -> sc_cli::Result<()> {
                         cmd.run::<Block, service::ExecutorDispatch>(config)
                     }
                     frame_benchmarking_cli::BenchmarkCmd::Block(cmd) => {
-                        let PartialComponents { client, .. } = service::new_partial(&config)?;
+                        let PartialComponents { client, .. } = service::new_partial(&config, cli.fork_choice, cli.max_reorg_depth)?;
                         cmd.run(client)
                     }
                     frame_benchmarking_cli::BenchmarkCmd::Storage(cmd) => {
                         let PartialComponents {
                             client, backend, ..
-                        } = service::new_partial(&config)?;
+                        } = service::new_partial(&config, cli.fork_choice, cli.max_reorg_depth)?;
                         let db = backend.expose_db();
                         let storage = backend.expose_storage();
 
                         cmd.run(config, client, db, storage)
                     }
                     frame_benchmarking_cli::BenchmarkCmd::Overhead(cmd) => {
-                        let PartialComponents { client, .. } = service::new_partial(&config)?;
+                        let PartialComponents { client, .. } = service::new_partial(&config, cli.fork_choice, cli.max_reorg_depth)?;
                         let ext_builder = RemarkBuilder::new(client.clone());
 
                         cmd.run(
@@ -168,7 +168,7 @@ pub fn run() -> sc_cli::Result<()> {
                         )
                     }
                     frame_benchmarking_cli::BenchmarkCmd::Extrinsic(cmd) => {
-                        let PartialComponents { client, .. } = service::new_partial(&config)?;
+                        let PartialComponents { client, .. } = service::new_partial(&config, cli.fork_choice, cli.max_reorg_depth)?;
                         // Register the *Remark* and *TKA* builders.
                         let ext_factory = ExtrinsicFactory(vec![
                             Box::new(RemarkBuilder::new(client.clone())),
@@ -219,7 +219,7 @@ pub fn run() -> sc_cli::Result<()> {
         None => {
             let runner = cli.create_runner(&cli.run)?;
             runner.run_node_until_exit(|config| async move {
-                service::new_full(config).map_err(sc_cli::Error::Service)
+                service::new_full(config, cli.fork_choice, cli.max_reorg_depth, cli.mining).map_err(sc_cli::Error::Service)
             })
         }
     }
@@ -0,0 +1,238 @@
+//! FlyClient prover.
+//!
+//! The runtime only keeps the peaks of the MMR, which is enough for headers to
+//! commit to it but not to prove a leaf. `MmrStore` keeps every node of the
+//! MMR over the best chain in the client's aux store, keyed by the last leaf
+//! it covers and its height, and catches up with the best chain whenever a
+//! proof is requested. After a reorg it rolls back to the common ancestor and
+//! rebuilds the nodes above it.
+//!
+//! Proofs are checked by `civicchain_primitives::flyclient::verify`.
+
+use crate::ghost::seal_difficulty;
+use civicchain_primitives::{
+    flyclient::{self, FlyClientProof, MAX_SECURITY_PARAM},
+    mmr::{bag_peaks, MmrCommitment, MmrNode},
+    yespower::PowSchedule,
+};
+use parity_scale_codec::{Decode, Encode};
+use sc_client_api::AuxStore;
+use sp_blockchain::{Error as BlockchainError, HeaderBackend};
+use sp_core::{H256, U256};
+use sp_runtime::{
+    traits::{Block as BlockT, Header as HeaderT},
+    SaturatedConversion,
+};
+use std::{
+    collections::HashMap,
+    iter,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+const NODE_PREFIX: &[u8] = b"flyclient:node:";
+const LEAF_PREFIX: &[u8] = b"flyclient:leaf:";
+const LEAF_COUNT_KEY: &[u8] = b"flyclient:leaf_count";
+
+/// Leaves appended per aux store write while catching up.
+const BATCH_SIZE: u64 = 1_024;
+
+fn node_key(last: u64, height: u32) -> Vec<u8> {
+    (NODE_PREFIX, last, height).encode()
+}
+
+fn leaf_key(leaf: u64) -> Vec<u8> {
+    (LEAF_PREFIX, leaf).encode()
+}
+
+fn load<T: Decode>(store: &impl AuxStore, key: &[u8]) -> sp_blockchain::Result<Option<T>> {
+    match store.get_aux(key)? {
+        Some(bytes) => T::decode(&mut &bytes[..])
+            .map(Some)
+            .map_err(|err| BlockchainError::Backend(format!("Corrupted FlyClient MMR: {}", err))),
+        None => Ok(None),
+    }
+}
+
+/// MMR nodes over the best chain, kept in the client's aux store.
+pub struct MmrStore<B, C> {
+    client: Arc<C>,
+    // Two requests catching up at once would write the same nodes.
+    sync_lock: Mutex<()>,
+    _phantom: PhantomData<B>,
+}
+
+impl<B, C> MmrStore<B, C>
+where
+    B: BlockT<Hash = H256>,
+    C: HeaderBackend<B> + AuxStore,
+{
+    /// Create a store on top of `client`.
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            sync_lock: Mutex::new(()),
+            _phantom: PhantomData,
+        }
+    }
+
+    fn node(&self, last: u64, height: u32) -> sp_blockchain::Result<Option<MmrNode>> {
+        load(&*self.client, &node_key(last, height))
+    }
+
+    fn canonical_hash(&self, number: u64) -> sp_blockchain::Result<Option<H256>> {
+        self.client.hash(number.saturated_into())
+    }
+
+    /// Append leaves until the MMR covers the best chain up to `leaf_count` leaves.
+    fn sync(&self, leaf_count: u64) -> sp_blockchain::Result<()> {
+        let _guard = self.sync_lock.lock().expect("FlyClient MMR lock poisoned");
+
+        // Drop leaves left behind by a reorg
+        let mut next: u64 = load(&*self.client, LEAF_COUNT_KEY)?.unwrap_or(0);
+        while next > 0 {
+            let stored: Option<H256> = load(&*self.client, &leaf_key(next - 1))?;
+            if stored.is_some() && stored == self.canonical_hash(next - 1)? {
+                break;
+            }
+            next -= 1;
+        }
+
+        while next < leaf_count {
+            let end = leaf_count.min(next + BATCH_SIZE);
+            let mut nodes = HashMap::new();
+            let mut leaves = Vec::new();
+
+            for leaf in next..end {
+                let hash = self
+                    .canonical_hash(leaf)?
+                    .ok_or(BlockchainError::UnknownBlock(format!("#{}", leaf)))?;
+                let header = self
+                    .client
+                    .header(hash)?
+                    .ok_or(BlockchainError::UnknownBlock(format!("{:?}", hash)))?;
+                // Genesis is the only block without a seal
+                let difficulty = match leaf {
+                    0 => U256::zero(),
+                    _ => seal_difficulty(header.digest().logs()).ok_or_else(|| {
+                        BlockchainError::Backend(format!("Block #{} has no seal", leaf))
+                    })?,
+                };
+
+                let mut node = MmrNode::leaf(hash, difficulty);
+                nodes.insert((leaf, 0), node);
+                for height in 0..(leaf + 1).trailing_zeros() {
+                    let sibling = leaf - (1 << height);
+                    let left = match nodes.get(&(sibling, height)) {
+                        Some(left) => *left,
+                        None => self.node(sibling, height)?.ok_or_else(|| {
+                            BlockchainError::Backend(format!(
+                                "Missing MMR node ({}, {})",
+                                sibling, height
+                            ))
+                        })?,
+                    };
+                    node = MmrNode::merge(&left, &node);
+                    nodes.insert((leaf, height + 1), node);
+                }
+                leaves.push((leaf, hash));
+            }
+
+            let values: Vec<(Vec<u8>, Vec<u8>)> = nodes
+                .iter()
+                .map(|((last, height), node)| (node_key(*last, *height), node.encode()))
+                .chain(
+                    leaves
+                        .iter()
+                        .map(|(leaf, hash)| (leaf_key(*leaf), hash.encode())),
+                )
+                .chain(iter::once((LEAF_COUNT_KEY.to_vec(), end.encode())))
+                .collect();
+            let insert: Vec<(&[u8], &[u8])> = values
+                .iter()
+                .map(|(key, value)| (&key[..], &value[..]))
+                .collect();
+            self.client.insert_aux(&insert, &[])?;
+            next = end;
+        }
+
+        Ok(())
+    }
+
+    /// FlyClient proof for `head`, which must be on the best chain, drawing
+    /// `security_param` samples. `schedule` holds the work function parameters
+    /// scheduled as of `head`, which the proof carries for the verifier.
+    pub fn proof(
+        &self,
+        head: H256,
+        security_param: u32,
+        schedule: PowSchedule,
+    ) -> sp_blockchain::Result<FlyClientProof<B::Header>> {
+        if security_param > MAX_SECURITY_PARAM {
+            return Err(BlockchainError::Application(
+                format!("Security parameter above {}", MAX_SECURITY_PARAM).into(),
+            ));
+        }
+
+        let header = self
+            .client
+            .header(head)?
+            .ok_or(BlockchainError::UnknownBlock(format!("{:?}", head)))?;
+        let number: u64 = (*header.number()).saturated_into();
+        if self.canonical_hash(number)? != Some(head) {
+            return Err(BlockchainError::Application(
+                format!("{:?} is not on the best chain", head).into(),
+            ));
+        }
+        let commitment = MmrCommitment::find(header.digest())
+            .filter(|commitment| commitment.leaf_count == number)
+            .ok_or_else(|| {
+                BlockchainError::Application(format!("{:?} has no MMR commitment", head).into())
+            })?;
+
+        self.sync(number)?;
+
+        // Nodes are read after catching up, so a lookup failure means the store
+        // is behind the chain it was just synced to.
+        let node = |last, height| self.node(last, height).ok().flatten();
+        let peaks = flyclient::mountains(number)
+            .iter()
+            .map(|mountain| node(mountain.last(), mountain.height))
+            .collect::<Option<Vec<_>>>();
+        if peaks.as_deref().and_then(bag_peaks) != Some(commitment.root) {
+            return Err(BlockchainError::Backend(
+                "FlyClient MMR does not match the header commitment".into(),
+            ));
+        }
+
+        let missing = || BlockchainError::Backend("Missing FlyClient MMR node".into());
+        let genesis = flyclient::prove(0, number, node).ok_or_else(missing)?;
+
+        let total = commitment.root.difficulty;
+        let head_hash = flyclient::header_hash(&header);
+        let samples = if total.is_zero() { 0 } else { security_param };
+        let samples = (0..samples)
+            .map(|index| {
+                let point =
+                    flyclient::sample_point(&head_hash, index, total).ok_or_else(missing)?;
+                let leaf = flyclient::find_leaf(point, number, node).ok_or_else(missing)?;
+                let proof = flyclient::prove(leaf, number, node).ok_or_else(missing)?;
+                let hash = self
+                    .canonical_hash(leaf)?
+                    .ok_or(BlockchainError::UnknownBlock(format!("#{}", leaf)))?;
+                let header = self
+                    .client
+                    .header(hash)?
+                    .ok_or(BlockchainError::UnknownBlock(format!("{:?}", hash)))?;
+                Ok((header, proof))
+            })
+            .collect::<sp_blockchain::Result<Vec<_>>>()?;
+
+        Ok(FlyClientProof {
+            head: header,
+            genesis,
+            samples,
+            schedule,
+        })
+    }
+}
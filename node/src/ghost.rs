@@ -0,0 +1,481 @@
+//! GHOST (Greedy Heaviest Observed Subtree) fork choice.
+//!
+//! Starting `FINALITY_DEPTH` blocks below the best block, GHOST repeatedly
+//! steps into the child whose subtree carries the most work, until it reaches
+//! a leaf. Every imported block counts, including stale forks, so work that
+//! lost a race still backs the branch it extends. On low-hashrate networks,
+//! where many blocks go stale, this makes the heaviest chain harder to outrun.
+//!
+//! The block tree and subtree weights are kept in the client's aux store and
+//! updated by `GhostBlockImport` in the same database transaction as the block
+//! itself. Levels deeper than `FINALITY_DEPTH` below the best block are never
+//! walked again, so they are pruned as the chain grows.
+//!
+//! `GhostSelectChain` answers `best_chain` for the mining worker, and
+//! `GhostBlockImport` sets the fork choice of every imported block so the
+//! client's best block follows the same rule. With `--fork-choice=longest`
+//! both fall back to the chain with the most accumulated difficulty.
+
+use civicchain_pow::CivicSeal;
+use futures::lock::Mutex;
+use parity_scale_codec::{Decode, Encode};
+use sc_client_api::{backend::Backend, AuxStore};
+use sc_consensus::{
+    BlockCheckParams, BlockImport, BlockImportParams, ForkChoiceStrategy, ImportResult,
+    LongestChain,
+};
+use sc_consensus_pow::PowAux;
+use sp_blockchain::{Error as BlockchainError, HeaderBackend};
+use sp_consensus::{Error as ConsensusError, SelectChain};
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::U256;
+use sp_runtime::{
+    traits::{Block as BlockT, Header as HeaderT, NumberFor},
+    DigestItem, SaturatedConversion,
+};
+use std::{collections::HashMap, sync::Arc};
+
+#[cfg(test)]
+mod tests;
+
+/// Blocks this far below the best block are treated as final by the fork choice:
+/// the GHOST walk starts there and the tree below it is pruned.
+const FINALITY_DEPTH: u64 = 100;
+
+const NODE_PREFIX: &[u8] = b"ghost:node:";
+const LEVEL_PREFIX: &[u8] = b"ghost:level:";
+const PRUNED_KEY: &[u8] = b"ghost:pruned";
+
+/// Rule used to pick the best block among competing forks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ForkChoice {
+    /// Chain with the most accumulated difficulty.
+    Longest,
+    /// Greedy heaviest observed subtree, counting the work of stale blocks.
+    Ghost,
+}
+
+/// Block in the GHOST tree.
+#[derive(Debug, Clone, Encode, Decode)]
+struct TreeNode<Hash> {
+    parent: Hash,
+    number: u64,
+    /// Work of this block alone.
+    difficulty: U256,
+    /// Work of this block and all of its known descendants.
+    weight: U256,
+    children: Vec<Hash>,
+}
+
+type NodeOf<B> = TreeNode<<B as BlockT>::Hash>;
+
+/// Tree changes made by one import, applied on top of the aux store.
+struct TreeUpdate<B: BlockT> {
+    nodes: HashMap<B::Hash, NodeOf<B>>,
+    levels: HashMap<u64, Vec<B::Hash>>,
+    pruned: Option<(u64, u64)>,
+}
+
+impl<B: BlockT> TreeUpdate<B> {
+    fn new() -> Self {
+        Self {
+            nodes: HashMap::new(),
+            levels: HashMap::new(),
+            pruned: None,
+        }
+    }
+}
+
+fn node_key<H: Encode>(hash: &H) -> Vec<u8> {
+    (NODE_PREFIX, hash).encode()
+}
+
+fn level_key(number: u64) -> Vec<u8> {
+    (LEVEL_PREFIX, number).encode()
+}
+
+fn load<T: Decode>(store: &impl AuxStore, key: &[u8]) -> sp_blockchain::Result<Option<T>> {
+    match store.get_aux(key)? {
+        Some(bytes) => T::decode(&mut &bytes[..])
+            .map(Some)
+            .map_err(|err| BlockchainError::Backend(format!("Corrupted GHOST tree: {}", err))),
+        None => Ok(None),
+    }
+}
+
+/// `SelectChain` following the configured fork choice rule.
+pub struct GhostSelectChain<B: BlockT, BE> {
+    backend: Arc<BE>,
+    longest: LongestChain<BE, B>,
+    fork_choice: ForkChoice,
+    // Imports from the network and from the local miner update shared ancestors.
+    import_lock: Arc<Mutex<()>>,
+}
+
+// Derive would require `B: Clone` and `BE: Clone`.
+impl<B: BlockT, BE> Clone for GhostSelectChain<B, BE> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            longest: self.longest.clone(),
+            fork_choice: self.fork_choice,
+            import_lock: self.import_lock.clone(),
+        }
+    }
+}
+
+impl<B, BE> GhostSelectChain<B, BE>
+where
+    B: BlockT,
+    BE: Backend<B>,
+{
+    /// Create a new instance using `fork_choice` over the blocks in `backend`.
+    pub fn new(backend: Arc<BE>, fork_choice: ForkChoice) -> Self {
+        Self {
+            longest: LongestChain::new(backend.clone()),
+            backend,
+            fork_choice,
+            import_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Number of the block the GHOST walk starts from.
+    fn root_number(&self) -> u64 {
+        let best: u64 = self
+            .backend
+            .blockchain()
+            .info()
+            .best_number
+            .saturated_into();
+        best.saturating_sub(FINALITY_DEPTH)
+    }
+
+    fn node(
+        &self,
+        hash: &B::Hash,
+        update: &TreeUpdate<B>,
+    ) -> sp_blockchain::Result<Option<NodeOf<B>>> {
+        match update.nodes.get(hash) {
+            Some(node) => Ok(Some(node.clone())),
+            None => load(self.backend.as_ref(), &node_key(hash)),
+        }
+    }
+
+    fn level(&self, number: u64, update: &TreeUpdate<B>) -> sp_blockchain::Result<Vec<B::Hash>> {
+        match update.levels.get(&number) {
+            Some(level) => Ok(level.clone()),
+            None => Ok(load(self.backend.as_ref(), &level_key(number))?.unwrap_or_default()),
+        }
+    }
+
+    /// Head chosen by GHOST with `update` applied, or `None` if the tree does not
+    /// reach down to the root yet (e.g. right after upgrading an existing node).
+    fn ghost_head(&self, update: &TreeUpdate<B>) -> sp_blockchain::Result<Option<B::Hash>> {
+        let root_number = self.root_number();
+        let root = match self
+            .backend
+            .blockchain()
+            .hash(root_number.saturated_into())?
+        {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+        let mut node = match self.node(&root, update)? {
+            Some(node) => node,
+            None => return Ok(None),
+        };
+
+        // Step into the heaviest child until a leaf is reached. Ties go to the
+        // child imported first.
+        let mut head = root;
+        loop {
+            let mut heaviest: Option<(B::Hash, NodeOf<B>)> = None;
+            for child in &node.children {
+                if let Some(child_node) = self.node(child, update)? {
+                    if heaviest
+                        .as_ref()
+                        .map_or(true, |(_, best)| child_node.weight > best.weight)
+                    {
+                        heaviest = Some((*child, child_node));
+                    }
+                }
+            }
+
+            match heaviest {
+                Some((hash, child_node)) => {
+                    head = hash;
+                    node = child_node;
+                }
+                None => return Ok(Some(head)),
+            }
+        }
+    }
+
+    /// Add a block to the tree, adding its work to the weight of every ancestor,
+    /// and prune levels that fell below the root.
+    fn insert(
+        &self,
+        hash: B::Hash,
+        header: &B::Header,
+        difficulty: U256,
+    ) -> sp_blockchain::Result<TreeUpdate<B>> {
+        let mut update = TreeUpdate::new();
+        let number: u64 = (*header.number()).saturated_into();
+        let parent = *header.parent_hash();
+
+        // The first block imported on top of an unknown parent (genesis, or a chain
+        // imported before the tree existed) starts the tree from that parent.
+        let mut parent_node = match self.node(&parent, &update)? {
+            Some(node) => node,
+            None => {
+                let difficulty = PowAux::<U256>::read::<_, B>(self.backend.as_ref(), &parent)
+                    .map_err(|err| BlockchainError::Backend(err.to_string()))?
+                    .difficulty;
+                let grandparent = self
+                    .backend
+                    .blockchain()
+                    .header(parent)?
+                    .map(|header| *header.parent_hash())
+                    .unwrap_or_default();
+
+                let parent_number = number.saturating_sub(1);
+                let mut level = self.level(parent_number, &update)?;
+                level.push(parent);
+                update.levels.insert(parent_number, level);
+
+                TreeNode {
+                    parent: grandparent,
+                    number: parent_number,
+                    difficulty,
+                    weight: difficulty,
+                    children: Vec::new(),
+                }
+            }
+        };
+        parent_node.children.push(hash);
+        update.nodes.insert(parent, parent_node);
+
+        update.nodes.insert(
+            hash,
+            TreeNode {
+                parent,
+                number,
+                difficulty,
+                weight: difficulty,
+                children: Vec::new(),
+            },
+        );
+        let mut level = self.level(number, &update)?;
+        level.push(hash);
+        update.levels.insert(number, level);
+
+        // Walk up to the root; weights below it are never compared.
+        let root_number = self.root_number();
+        let mut ancestor = Some(parent);
+        while let Some(current) = ancestor {
+            ancestor = match self.node(&current, &update)? {
+                Some(mut node) if node.number >= root_number => {
+                    node.weight = node.weight.saturating_add(difficulty);
+                    let next = node.parent;
+                    update.nodes.insert(current, node);
+                    Some(next)
+                }
+                _ => None,
+            };
+        }
+
+        // Nothing below the root is in the tree the first time it is written, since
+        // it starts at the parent of the first block imported. Pruning counts from
+        // there, rather than from genesis on a node upgraded with a long chain.
+        match load::<u64>(self.backend.as_ref(), PRUNED_KEY)? {
+            Some(pruned) if root_number > pruned => {
+                update.pruned = Some((pruned, root_number));
+            }
+            Some(_) => {}
+            None => update.pruned = Some((root_number, root_number)),
+        }
+
+        Ok(update)
+    }
+
+    /// Aux store operations applying `update`. Blocks below the pruning point,
+    /// such as a fork off a pruned block, are not kept.
+    fn aux_ops(
+        &self,
+        update: &TreeUpdate<B>,
+    ) -> sp_blockchain::Result<Vec<(Vec<u8>, Option<Vec<u8>>)>> {
+        let mut ops = Vec::new();
+        let keep_from = match update.pruned {
+            Some((_, to)) => to,
+            None => load(self.backend.as_ref(), PRUNED_KEY)?.unwrap_or(0),
+        };
+
+        if let Some((from, to)) = update.pruned {
+            for number in from..to {
+                for hash in self.level(number, &TreeUpdate::new())? {
+                    ops.push((node_key(&hash), None));
+                }
+                ops.push((level_key(number), None));
+            }
+            ops.push((PRUNED_KEY.to_vec(), Some(to.encode())));
+        }
+
+        for (hash, node) in update
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.number >= keep_from)
+        {
+            ops.push((node_key(hash), Some(node.encode())));
+        }
+        for (number, level) in update
+            .levels
+            .iter()
+            .filter(|(number, _)| **number >= keep_from)
+        {
+            ops.push((level_key(*number), Some(level.encode())));
+        }
+
+        Ok(ops)
+    }
+}
+
+#[async_trait::async_trait]
+impl<B, BE> SelectChain<B> for GhostSelectChain<B, BE>
+where
+    B: BlockT,
+    BE: Backend<B>,
+{
+    async fn leaves(&self) -> Result<Vec<B::Hash>, ConsensusError> {
+        self.longest.leaves().await
+    }
+
+    async fn best_chain(&self) -> Result<B::Header, ConsensusError> {
+        if self.fork_choice == ForkChoice::Longest {
+            return self.longest.best_chain().await;
+        }
+
+        let head = match self
+            .ghost_head(&TreeUpdate::new())
+            .map_err(|err| ConsensusError::ChainLookup(err.to_string()))?
+        {
+            Some(head) => head,
+            None => return self.longest.best_chain().await,
+        };
+
+        self.backend
+            .blockchain()
+            .header(head)
+            .ok()
+            .flatten()
+            .ok_or_else(|| {
+                ConsensusError::ChainLookup(format!("Missing header of GHOST head {:?}", head))
+            })
+    }
+
+    async fn finality_target(
+        &self,
+        base_hash: B::Hash,
+        maybe_max_number: Option<NumberFor<B>>,
+    ) -> Result<B::Hash, ConsensusError> {
+        self.longest
+            .finality_target(base_hash, maybe_max_number)
+            .await
+    }
+}
+
+/// Block import maintaining the GHOST tree and, in GHOST mode, setting the fork
+/// choice of each block, before handing it to `inner` (usually `PowBlockImport`,
+/// which verifies the seal).
+pub struct GhostBlockImport<B: BlockT, I, BE> {
+    inner: I,
+    select_chain: GhostSelectChain<B, BE>,
+}
+
+impl<B: BlockT, I, BE> GhostBlockImport<B, I, BE> {
+    /// Wrap `inner`, choosing forks with `select_chain`.
+    pub fn new(inner: I, select_chain: GhostSelectChain<B, BE>) -> Self {
+        Self {
+            inner,
+            select_chain,
+        }
+    }
+}
+
+impl<B: BlockT, I: Clone, BE> Clone for GhostBlockImport<B, I, BE> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            select_chain: self.select_chain.clone(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<B, I, BE> BlockImport<B> for GhostBlockImport<B, I, BE>
+where
+    B: BlockT,
+    I: BlockImport<B, Error = ConsensusError> + Send,
+    BE: Backend<B>,
+{
+    type Error = ConsensusError;
+    type Transaction = I::Transaction;
+
+    async fn check_block(
+        &mut self,
+        block: BlockCheckParams<B>,
+    ) -> Result<ImportResult, Self::Error> {
+        self.inner.check_block(block).await
+    }
+
+    async fn import_block(
+        &mut self,
+        mut block: BlockImportParams<B, Self::Transaction>,
+    ) -> Result<ImportResult, Self::Error> {
+        // Blocks without a valid seal are rejected by the inner import anyway.
+        let difficulty = match seal_difficulty(&block.post_digests) {
+            Some(difficulty) => difficulty,
+            None => return self.inner.import_block(block).await,
+        };
+
+        let lookup_error = |err: BlockchainError| ConsensusError::ChainLookup(err.to_string());
+        let import_lock = self.select_chain.import_lock.clone();
+        let _guard = import_lock.lock().await;
+
+        let hash = block.post_hash();
+        let update = self
+            .select_chain
+            .insert(hash, &block.header, difficulty)
+            .map_err(lookup_error)?;
+
+        if self.select_chain.fork_choice == ForkChoice::Ghost && block.fork_choice.is_none() {
+            let head = self
+                .select_chain
+                .ghost_head(&update)
+                .map_err(lookup_error)?;
+
+            // A stale block can also shift weight towards another existing
+            // branch; the client follows once a block on that branch arrives,
+            // while `best_chain` already mines on it.
+            if let Some(head) = head {
+                block.fork_choice = Some(ForkChoiceStrategy::Custom(head == hash));
+            }
+        }
+
+        // Written atomically with the block, so the tree never gets ahead of the chain.
+        block
+            .auxiliary
+            .extend(self.select_chain.aux_ops(&update).map_err(lookup_error)?);
+
+        self.inner.import_block(block).await
+    }
+}
+
+/// Difficulty committed to by the PoW seal among `post_digests`.
+pub(crate) fn seal_difficulty(post_digests: &[DigestItem]) -> Option<U256> {
+    post_digests.iter().rev().find_map(|digest| match digest {
+        DigestItem::Seal(id, seal) if *id == POW_ENGINE_ID => {
+            CivicSeal::decode_raw(seal).map(|seal| seal.difficulty)
+        }
+        _ => None,
+    })
+}
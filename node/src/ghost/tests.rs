@@ -0,0 +1,213 @@
+use super::*;
+use crate::test_utils::{self, TestImport};
+use civicchain_runtime::opaque::Block;
+use sc_client_api::{backend::NewBlockState, in_mem};
+use sp_core::H256;
+
+type TestBackend = in_mem::Backend<Block>;
+
+// Block tree over an in-memory backend, starting at a genesis block.
+struct TestChain {
+    backend: Arc<TestBackend>,
+    select_chain: GhostSelectChain<Block, TestBackend>,
+    import: GhostBlockImport<Block, TestImport, TestBackend>,
+    genesis: H256,
+}
+
+impl TestChain {
+    fn new(fork_choice: ForkChoice) -> Self {
+        let backend = Arc::new(TestBackend::new());
+        let genesis = test_utils::genesis(backend.blockchain());
+
+        let select_chain = GhostSelectChain::new(backend.clone(), fork_choice);
+        let import = GhostBlockImport::new(
+            TestImport {
+                blockchain: backend.blockchain().clone(),
+            },
+            select_chain.clone(),
+        );
+        Self {
+            backend,
+            select_chain,
+            import,
+            genesis,
+        }
+    }
+
+    fn block(&self, parent: H256, salt: u8, difficulty: u64) -> BlockImportParams<Block, ()> {
+        test_utils::block(self.backend.blockchain(), parent, salt, difficulty)
+    }
+
+    // Import a block on top of `parent` and return its hash.
+    async fn import(&mut self, parent: H256, salt: u8, difficulty: u64) -> H256 {
+        let block = self.block(parent, salt, difficulty);
+        let hash = block.post_hash();
+        self.import.import_block(block).await.unwrap();
+        hash
+    }
+
+    // Import `length` blocks of `difficulty` on top of `parent` and return the tip.
+    async fn extend(&mut self, mut parent: H256, length: u32, salt: u8, difficulty: u64) -> H256 {
+        for _ in 0..length {
+            parent = self.import(parent, salt, difficulty).await;
+        }
+        parent
+    }
+
+    async fn best_chain(&self) -> H256 {
+        self.select_chain.best_chain().await.unwrap().hash()
+    }
+
+    fn client_best(&self) -> H256 {
+        self.backend.blockchain().info().best_hash
+    }
+
+    fn node(&self, hash: &H256) -> Option<NodeOf<Block>> {
+        load(self.backend.as_ref(), &node_key(hash)).unwrap()
+    }
+}
+
+#[tokio::test]
+async fn subtree_weight_counts_every_descendant() {
+    let mut chain = TestChain::new(ForkChoice::Ghost);
+    let a1 = chain.import(chain.genesis, 1, 10).await;
+    let a2 = chain.import(a1, 1, 20).await;
+    let b2 = chain.import(a1, 2, 5).await;
+
+    assert_eq!(chain.node(&a2).unwrap().weight, U256::from(20));
+    assert_eq!(chain.node(&b2).unwrap().weight, U256::from(5));
+    let a1_node = chain.node(&a1).unwrap();
+    assert_eq!(a1_node.difficulty, U256::from(10));
+    assert_eq!(a1_node.weight, U256::from(35));
+    assert_eq!(a1_node.children, vec![a2, b2]);
+    assert_eq!(chain.node(&chain.genesis).unwrap().weight, U256::from(35));
+}
+
+#[tokio::test]
+async fn heavier_subtree_beats_longer_chain() {
+    let mut chain = TestChain::new(ForkChoice::Ghost);
+    let a3 = chain.extend(chain.genesis, 3, 1, 10).await;
+    assert_eq!(chain.best_chain().await, a3);
+    assert_eq!(chain.client_best(), a3);
+
+    // A fork whose tip is lower but whose stale siblings carry more work
+    let b1 = chain.import(chain.genesis, 2, 10).await;
+    let b2 = chain.import(b1, 3, 10).await;
+    chain.import(b1, 4, 10).await;
+    chain.import(b1, 5, 10).await;
+
+    // `best_chain` already mines on the heavier fork, while the client follows
+    // once a block on it becomes the head
+    assert_eq!(chain.best_chain().await, b2);
+    assert_eq!(chain.client_best(), a3);
+
+    let b3 = chain.import(b2, 6, 10).await;
+    assert_eq!(chain.best_chain().await, b3);
+    assert_eq!(chain.client_best(), b3);
+}
+
+#[tokio::test]
+async fn ties_go_to_the_child_imported_first() {
+    let mut chain = TestChain::new(ForkChoice::Ghost);
+    let a1 = chain.import(chain.genesis, 1, 10).await;
+    let b1 = chain.import(chain.genesis, 2, 10).await;
+    assert_eq!(chain.best_chain().await, a1);
+    assert_eq!(chain.client_best(), a1);
+
+    // A heavier sibling takes over as soon as it arrives
+    let c1 = chain.import(chain.genesis, 3, 11).await;
+    assert_eq!(chain.best_chain().await, c1);
+    assert_eq!(chain.client_best(), c1);
+    assert_ne!(chain.client_best(), b1);
+}
+
+#[tokio::test]
+async fn longest_chain_ignores_stale_work() {
+    let mut chain = TestChain::new(ForkChoice::Longest);
+    let a3 = chain.extend(chain.genesis, 3, 1, 10).await;
+
+    let b1 = chain.import(chain.genesis, 2, 10).await;
+    chain.import(b1, 3, 10).await;
+    chain.import(b1, 4, 10).await;
+    chain.import(b1, 5, 10).await;
+
+    // The tree is still kept, so switching rules later has the weights at hand
+    assert_eq!(chain.node(&b1).unwrap().weight, U256::from(40));
+    assert_eq!(chain.best_chain().await, a3);
+    assert_eq!(chain.client_best(), a3);
+}
+
+#[tokio::test]
+async fn levels_below_the_root_are_pruned() {
+    let mut chain = TestChain::new(ForkChoice::Ghost);
+    let a1 = chain.import(chain.genesis, 1, 10).await;
+    let stale = chain.import(chain.genesis, 2, 10).await;
+    let tip = chain.extend(a1, FINALITY_DEPTH as u32 + 4, 1, 10).await;
+
+    // The root is `FINALITY_DEPTH` below the best block, at height 5 before the
+    // tip was imported
+    let pruned: u64 = load(chain.backend.as_ref(), PRUNED_KEY).unwrap().unwrap();
+    assert_eq!(pruned, 4);
+    for hash in [chain.genesis, a1, stale] {
+        assert!(chain.node(&hash).is_none());
+    }
+    for number in 0..pruned {
+        assert!(chain.backend.get_aux(&level_key(number)).unwrap().is_none());
+    }
+    assert!(chain.backend.get_aux(&level_key(pruned)).unwrap().is_some());
+
+    // The walk starts at the root, so the fork choice still works
+    assert_eq!(chain.best_chain().await, tip);
+}
+
+#[tokio::test]
+async fn forks_below_the_pruning_point_are_not_stored() {
+    let mut chain = TestChain::new(ForkChoice::Ghost);
+    let a1 = chain.import(chain.genesis, 1, 10).await;
+    let tip = chain.extend(a1, FINALITY_DEPTH as u32 + 4, 1, 10).await;
+
+    // The first fork prunes one more level, the second one prunes nothing
+    let b2 = chain.import(a1, 2, 10).await;
+    let c2 = chain.import(a1, 3, 10).await;
+    let pruned: u64 = load(chain.backend.as_ref(), PRUNED_KEY).unwrap().unwrap();
+    assert_eq!(pruned, 5);
+    for hash in [a1, b2, c2] {
+        assert!(chain.node(&hash).is_none());
+    }
+    for number in 0..pruned {
+        assert!(chain.backend.get_aux(&level_key(number)).unwrap().is_none());
+    }
+    assert_eq!(chain.best_chain().await, tip);
+}
+
+#[tokio::test]
+async fn first_import_on_an_existing_chain_prunes_nothing() {
+    let mut chain = TestChain::new(ForkChoice::Ghost);
+
+    // A chain imported before the tree existed
+    let mut parent = chain.genesis;
+    for _ in 0..FINALITY_DEPTH + 50 {
+        let block = chain.block(parent, 1, 10);
+        parent = block.post_hash();
+        chain
+            .backend
+            .blockchain()
+            .insert(parent, block.post_header(), None, None, NewBlockState::Best)
+            .unwrap();
+    }
+
+    let header = chain.block(parent, 1, 10).header;
+    let update = chain
+        .select_chain
+        .insert(header.hash(), &header, U256::from(10))
+        .unwrap();
+    let ops = chain.select_chain.aux_ops(&update).unwrap();
+    assert!(ops.iter().all(|(_, value)| value.is_some()));
+    assert!(ops.contains(&(PRUNED_KEY.to_vec(), Some(50u64.encode()))));
+
+    // Later imports prune from there, a level at a time
+    let tip = chain.import(parent, 1, 10).await;
+    chain.import(tip, 1, 10).await;
+    let pruned: u64 = load(chain.backend.as_ref(), PRUNED_KEY).unwrap().unwrap();
+    assert_eq!(pruned, 51);
+}
@@ -6,10 +6,21 @@
 #![warn(missing_docs)]
 
 mod chain_spec;
+mod checkpoint;
 mod cli;
 mod command;
+mod flyclient;
+mod ghost;
+mod miner;
+mod poh;
+mod pow;
+mod reorg;
 mod rpc;
 mod service;
+#[cfg(feature = "stratum")]
+mod stratum;
+#[cfg(test)]
+mod test_utils;
 
 fn main() -> sc_cli::Result<()> {
     command::run()
@@ -0,0 +1,225 @@
+//! Built-in multi-threaded CPU miner.
+//!
+//! Each thread polls the mining worker for the block currently being built,
+//! hashes a batch of nonces with Yespower-R16 and submits any seal that meets
+//! the difficulty. Batches are kept small so threads notice new builds quickly.
+
+use crate::{
+    poh::PohClock,
+    pow::{self, YespowerAlgorithm},
+};
+use civicchain_pow::{CivicPowApi, PowParams};
+use civicchain_runtime::{AccountId, Balance};
+use futures::{future::BoxFuture, FutureExt};
+use log::{info, warn};
+use sc_consensus::JustificationSyncLink;
+use sc_consensus_pow::MiningHandle;
+use sp_api::ProvideRuntimeApi;
+use sp_consensus_pow::{PowApi, Seal as RawSeal};
+use sp_core::{H256, U256};
+use sp_runtime::traits::Block as BlockT;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Nonces tried before checking the worker for a newer build.
+const NONCES_PER_ROUND: u64 = 64;
+
+/// How long an idle thread waits for the worker to produce a build.
+const IDLE_WAIT: Duration = Duration::from_millis(500);
+
+/// Period over which the local hashrate is averaged.
+const HASHRATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Work currently offered by the mining worker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Work {
+    /// Build version, bumped whenever the worker starts on a new block.
+    pub version: usize,
+    /// Hash the seal must commit to.
+    pub pre_hash: H256,
+    /// Difficulty the seal must meet.
+    pub difficulty: U256,
+    /// End of the block's PoH sequence, which the seal must refer to.
+    pub poh: H256,
+    /// Work function parameters in force at the block's height.
+    pub params: PowParams,
+}
+
+/// Source of work for miners, and sink for the seals they find.
+pub trait WorkSource: Clone + Send + Sync + 'static {
+    /// Work for the block currently being built, if any.
+    fn work(&self) -> Option<Work>;
+
+    /// Submit an encoded seal for the current build. Resolves to whether the block was imported.
+    fn submit(&self, seal: RawSeal) -> BoxFuture<'static, bool>;
+}
+
+/// Mining worker together with the PoH clock feeding its builds and the
+/// algorithm telling which work function parameters they are mined with.
+#[derive(Clone)]
+pub struct PowWorker<H, A> {
+    handle: H,
+    poh: PohClock,
+    algorithm: A,
+    // Parameters for builds on the last parent, so threads do not call into the runtime
+    // every round.
+    params: Arc<Mutex<Option<(H256, PowParams)>>>,
+}
+
+impl<H, A> PowWorker<H, A> {
+    /// Pair the mining worker `handle` with the clock its builds take PoH sequences from
+    /// and the `algorithm` it seals with.
+    pub fn new(handle: H, poh: PohClock, algorithm: A) -> Self {
+        Self {
+            handle,
+            poh,
+            algorithm,
+            params: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl<B, C, L, Proof> WorkSource
+    for PowWorker<MiningHandle<B, YespowerAlgorithm<C>, L, Proof>, YespowerAlgorithm<C>>
+where
+    B: BlockT<Hash = H256>,
+    C: ProvideRuntimeApi<B> + Send + Sync + 'static,
+    C::Api: PowApi<B> + CivicPowApi<B, AccountId, Balance>,
+    L: JustificationSyncLink<B> + 'static,
+    Proof: Send + 'static,
+{
+    fn work(&self) -> Option<Work> {
+        let metadata = self.handle.metadata()?;
+        let poh = self.poh.issued(metadata.best_hash)?;
+        let params = {
+            let mut cached = self
+                .params
+                .lock()
+                .expect("work parameters lock is never poisoned; qed");
+            match &*cached {
+                Some((parent, params)) if *parent == metadata.best_hash => params.clone(),
+                _ => {
+                    let params = self.algorithm.params(metadata.best_hash).ok()?;
+                    *cached = Some((metadata.best_hash, params.clone()));
+                    params
+                }
+            }
+        };
+
+        Some(Work {
+            version: self.handle.version(),
+            pre_hash: metadata.pre_hash,
+            difficulty: metadata.difficulty,
+            poh: poh.hash,
+            params,
+        })
+    }
+
+    fn submit(&self, seal: RawSeal) -> BoxFuture<'static, bool> {
+        let worker = self.handle.clone();
+        async move { worker.submit(seal).await }.boxed()
+    }
+}
+
+/// Hashes computed by the built-in miner, shared between miner threads and RPC.
+#[derive(Clone)]
+pub struct Hashrate {
+    hashes: Arc<AtomicU64>,
+    // Start of the current averaging window and the hash count at that time.
+    window: Arc<Mutex<(Instant, u64)>>,
+}
+
+impl Default for Hashrate {
+    fn default() -> Self {
+        Self {
+            hashes: Arc::new(AtomicU64::new(0)),
+            window: Arc::new(Mutex::new((Instant::now(), 0))),
+        }
+    }
+}
+
+impl Hashrate {
+    fn record(&self, hashes: u64) {
+        self.hashes.fetch_add(hashes, Ordering::Relaxed);
+    }
+
+    /// Hashes per second over roughly the last `HASHRATE_WINDOW`.
+    pub fn hashes_per_second(&self) -> u64 {
+        let hashes = self.hashes.load(Ordering::Relaxed);
+        let mut window = self
+            .window
+            .lock()
+            .expect("hashrate lock is never poisoned; qed");
+
+        let elapsed = window.0.elapsed();
+        let rate = if elapsed.is_zero() {
+            0
+        } else {
+            (hashes.saturating_sub(window.1) as f64 / elapsed.as_secs_f64()) as u64
+        };
+
+        // Start a new window once the current one is stale.
+        if elapsed > HASHRATE_WINDOW {
+            *window = (Instant::now(), hashes);
+        }
+
+        rate
+    }
+}
+
+/// Spawn `threads` hashing threads mining on top of `worker`.
+pub fn start<W: WorkSource>(worker: W, threads: usize, hashrate: Hashrate) {
+    let threads = threads.max(1);
+    info!("⛏  Starting Yespower-R16 miner with {} thread(s)", threads);
+
+    for index in 0..threads {
+        let worker = worker.clone();
+        let hashrate = hashrate.clone();
+
+        thread::Builder::new()
+            .name(format!("yespower-miner-{}", index))
+            .spawn(move || mine_loop(worker, hashrate))
+            .expect("spawning a miner thread should not fail; qed");
+    }
+}
+
+fn mine_loop<W: WorkSource>(worker: W, hashrate: Hashrate) {
+    // Start each thread at a random nonce so threads do not repeat each other's work.
+    let mut nonce: u64 = rand::random();
+
+    loop {
+        let work = match worker.work() {
+            Some(work) => work,
+            None => {
+                thread::sleep(IDLE_WAIT);
+                continue;
+            }
+        };
+
+        let found = pow::mine(
+            &work.params,
+            &work.pre_hash,
+            work.difficulty,
+            work.poh,
+            nonce,
+            NONCES_PER_ROUND,
+        );
+        hashrate.record(NONCES_PER_ROUND);
+
+        if let Some(seal) = found {
+            if futures::executor::block_on(worker.submit(seal)) {
+                info!("⛏  Found seal for pre-hash {}", work.pre_hash);
+            } else {
+                warn!("⛏  Seal for pre-hash {} was not accepted", work.pre_hash);
+            }
+        }
+
+        nonce = nonce.wrapping_add(NONCES_PER_ROUND);
+    }
+}
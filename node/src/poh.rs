@@ -0,0 +1,186 @@
+//! Proof-of-History clock and import check.
+//!
+//! `PohClock` keeps ticking the sequence of the best block on a background
+//! thread, so by the time a block is built on it the sequence holds as many
+//! ticks as fit between the two blocks, up to the runtime's `MaxPohTicks`.
+//! `PohInherentDataProvider` hands that sequence to the runtime, which checks
+//! it and deposits it as a header digest, and miners refer to it in their seals.
+//! `PohBlockImport` rejects imported blocks whose digest does not follow from
+//! their parent hash, or whose seal refers to another sequence.
+
+use civicchain_pow::{poh, CivicSeal, PohDigest};
+use sc_consensus::{BlockCheckParams, BlockImport, BlockImportParams, ImportResult};
+use sp_blockchain::HeaderBackend;
+use sp_consensus::Error as ConsensusError;
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::H256;
+use sp_inherents::{InherentData, InherentIdentifier};
+use sp_runtime::{
+    traits::{Block as BlockT, Header as HeaderT},
+    DigestItem,
+};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// Ticks run between checks for a new best block.
+const TICKS_PER_ROUND: u64 = 1_000;
+
+/// How long the clock sleeps once it has reached `MaxPohTicks`.
+const IDLE_WAIT: Duration = Duration::from_millis(100);
+
+/// Maximum ticks between two blocks, as enforced by the runtime.
+fn max_ticks() -> u64 {
+    civicchain_runtime::MaxPohTicks::get()
+}
+
+/// PoH sequence of the best block, advanced on a background thread.
+#[derive(Clone)]
+pub struct PohClock {
+    /// Block the sequence was seeded from, and the sequence so far.
+    state: Arc<Mutex<(H256, PohDigest)>>,
+    /// Last sequence handed to a block build, and the parent it was built on.
+    issued: Arc<Mutex<Option<(H256, PohDigest)>>>,
+}
+
+impl PohClock {
+    /// Start ticking on top of the best block of `client`, restarting whenever it changes.
+    pub fn start<B, C>(client: Arc<C>) -> Self
+    where
+        B: BlockT<Hash = H256>,
+        C: HeaderBackend<B> + 'static,
+    {
+        let best = client.info().best_hash;
+        let clock = Self {
+            state: Arc::new(Mutex::new((best, PohDigest::new(best)))),
+            issued: Arc::new(Mutex::new(None)),
+        };
+
+        let ticking = clock.clone();
+        thread::Builder::new()
+            .name("poh-clock".into())
+            .spawn(move || loop {
+                let best = client.info().best_hash;
+                if !ticking.tick(best) {
+                    thread::sleep(IDLE_WAIT);
+                }
+            })
+            .expect("Failed to spawn the Proof-of-History clock thread");
+
+        clock
+    }
+
+    /// Run a round of ticks on top of `best`. Returns false once the sequence is full.
+    fn tick(&self, best: H256) -> bool {
+        let mut state = self.state.lock().expect("PoH clock lock poisoned");
+        if state.0 != best {
+            *state = (best, PohDigest::new(best));
+        }
+
+        let ticks = max_ticks()
+            .saturating_sub(state.1.ticks)
+            .min(TICKS_PER_ROUND);
+        state.1.extend(ticks);
+        ticks > 0
+    }
+
+    /// Sequence to put in a block built on `parent`.
+    ///
+    /// Building on a block other than the one the clock follows gets a sequence
+    /// with no ticks, which is valid but proves no elapsed time.
+    pub fn sequence(&self, parent: H256) -> PohDigest {
+        let sequence = match *self.state.lock().expect("PoH clock lock poisoned") {
+            (seed, sequence) if seed == parent => sequence,
+            _ => PohDigest::new(parent),
+        };
+
+        *self.issued.lock().expect("PoH clock lock poisoned") = Some((parent, sequence));
+        sequence
+    }
+
+    /// Sequence last handed to a block built on `parent`, which the seal of
+    /// that block has to refer to.
+    pub fn issued(&self, parent: H256) -> Option<PohDigest> {
+        match *self.issued.lock().expect("PoH clock lock poisoned") {
+            Some((seed, sequence)) if seed == parent => Some(sequence),
+            _ => None,
+        }
+    }
+}
+
+/// Provides the PoH sequence of the block being built.
+pub struct PohInherentDataProvider(pub PohDigest);
+
+#[async_trait::async_trait]
+impl sp_inherents::InherentDataProvider for PohInherentDataProvider {
+    async fn provide_inherent_data(
+        &self,
+        inherent_data: &mut InherentData,
+    ) -> Result<(), sp_inherents::Error> {
+        inherent_data.put_data(poh::INHERENT_IDENTIFIER, &self.0)
+    }
+
+    async fn try_handle_error(
+        &self,
+        _: &InherentIdentifier,
+        _: &[u8],
+    ) -> Option<Result<(), sp_inherents::Error>> {
+        None
+    }
+}
+
+/// Block import wrapper rejecting blocks without a valid PoH sequence.
+#[derive(Clone)]
+pub struct PohBlockImport<I> {
+    inner: I,
+}
+
+impl<I> PohBlockImport<I> {
+    /// Wrap `inner`, which only sees blocks whose sequence checks out.
+    pub fn new(inner: I) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait::async_trait]
+impl<B, I> BlockImport<B> for PohBlockImport<I>
+where
+    B: BlockT<Hash = H256>,
+    I: BlockImport<B, Error = ConsensusError> + Send,
+{
+    type Error = ConsensusError;
+    type Transaction = I::Transaction;
+
+    async fn check_block(
+        &mut self,
+        block: BlockCheckParams<B>,
+    ) -> Result<ImportResult, Self::Error> {
+        self.inner.check_block(block).await
+    }
+
+    async fn import_block(
+        &mut self,
+        block: BlockImportParams<B, Self::Transaction>,
+    ) -> Result<ImportResult, Self::Error> {
+        if !poh::verify_header(&block.header, max_ticks()) {
+            return Err(ConsensusError::ClientImport(
+                "Block has a missing or invalid Proof-of-History sequence".into(),
+            ));
+        }
+
+        // The seal has been moved to the post-digests by the time a block is imported
+        let seal = block.post_digests.last().and_then(|item| match item {
+            DigestItem::Seal(id, seal) if *id == POW_ENGINE_ID => CivicSeal::decode_raw(seal),
+            _ => None,
+        });
+        if !seal.map_or(false, |seal| seal.follows_poh(block.header.digest())) {
+            return Err(ConsensusError::ClientImport(
+                "Block seal does not refer to its Proof-of-History sequence".into(),
+            ));
+        }
+
+        self.inner.import_block(block).await
+    }
+}
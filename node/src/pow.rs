@@ -0,0 +1,119 @@
+//! Yespower-R16 implementation of `sc_consensus_pow::PowAlgorithm`.
+//!
+//! Difficulty is read from the runtime through `PowApi`, and seals are
+//! checked with the same work function the PoW pallet uses. The work function
+//! parameters in force at a height, which governance may schedule, are read
+//! from the runtime through `CivicPowApi` at the parent block.
+
+use civicchain_pow::{compute_work, meets_difficulty, CivicPowApi, CivicSeal, PowParams};
+use civicchain_runtime::{AccountId, Balance};
+use parity_scale_codec::Encode;
+use sc_consensus_pow::{Error, PowAlgorithm};
+use sp_api::ProvideRuntimeApi;
+use sp_consensus_pow::{PowApi, Seal as RawSeal};
+use sp_core::{H256, U256};
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use std::sync::Arc;
+
+/// Yespower-R16 proof of work algorithm.
+pub struct YespowerAlgorithm<C> {
+    client: Arc<C>,
+}
+
+impl<C> YespowerAlgorithm<C> {
+    /// Create a new instance backed by `client`.
+    pub fn new(client: Arc<C>) -> Self {
+        Self { client }
+    }
+}
+
+impl<C> YespowerAlgorithm<C> {
+    /// Work function parameters of a block built on `parent`.
+    pub fn params<B>(&self, parent: B::Hash) -> Result<PowParams, Error<B>>
+    where
+        B: BlockT<Hash = H256>,
+        C: ProvideRuntimeApi<B>,
+        C::Api: CivicPowApi<B, AccountId, Balance>,
+    {
+        self.client.runtime_api().pow_params(parent).map_err(|err| {
+            Error::Environment(format!(
+                "Fetching work function parameters from runtime failed: {:?}",
+                err
+            ))
+        })
+    }
+}
+
+// Derive would require `C: Clone`.
+impl<C> Clone for YespowerAlgorithm<C> {
+    fn clone(&self) -> Self {
+        Self::new(self.client.clone())
+    }
+}
+
+impl<B, C> PowAlgorithm<B> for YespowerAlgorithm<C>
+where
+    B: BlockT<Hash = H256>,
+    C: ProvideRuntimeApi<B>,
+    C::Api: PowApi<B> + CivicPowApi<B, AccountId, Balance>,
+{
+    type Difficulty = U256;
+
+    fn difficulty(&self, parent: B::Hash) -> Result<Self::Difficulty, Error<B>> {
+        self.client.runtime_api().difficulty(parent).map_err(|err| {
+            Error::Environment(format!("Fetching difficulty from runtime failed: {:?}", err))
+        })
+    }
+
+    fn verify(
+        &self,
+        parent: &BlockId<B>,
+        pre_hash: &H256,
+        _pre_digest: Option<&[u8]>,
+        seal: &RawSeal,
+        difficulty: Self::Difficulty,
+    ) -> Result<bool, Error<B>> {
+        // Oversize seals and unknown versions are rejected before decoding.
+        let seal = match CivicSeal::decode_raw(seal) {
+            Some(seal) => seal,
+            None => return Ok(false),
+        };
+
+        // The seal must commit to the difficulty the chain expects, not a lower one.
+        if seal.difficulty != difficulty {
+            return Ok(false);
+        }
+
+        let parent = match parent {
+            BlockId::Hash(hash) => *hash,
+            BlockId::Number(_) => {
+                return Err(Error::Environment(
+                    "Seals are verified against the parent hash".into(),
+                ))
+            }
+        };
+        let params = self.params(parent)?;
+
+        Ok(seal.verify(&params, pre_hash.as_bytes()))
+    }
+}
+
+/// Try `rounds` consecutive nonces starting at `start` and return the first
+/// encoded seal whose work under `params` meets `difficulty`, referring to the
+/// PoH sequence `poh`.
+pub fn mine(
+    params: &PowParams,
+    pre_hash: &H256,
+    difficulty: U256,
+    poh: H256,
+    start: u64,
+    rounds: u64,
+) -> Option<RawSeal> {
+    (start..start.saturating_add(rounds)).find_map(|nonce| {
+        let nonce = nonce.to_le_bytes();
+        let work = compute_work(params, pre_hash.as_bytes(), &nonce);
+
+        meets_difficulty(&work, difficulty)
+            .then(|| CivicSeal::new(difficulty, nonce, poh, work).encode())
+    })
+}
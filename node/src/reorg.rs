@@ -0,0 +1,207 @@
+//! Reorg depth limit and reorg notifications.
+//!
+//! Without finality nothing bounds how far back a heavier fork can rewrite the
+//! chain. `ReorgBlockImport` sits below `PowBlockImport`, where the fork choice
+//! of a block is known, and refuses blocks that would retract more than
+//! `--max-reorg-depth` blocks of the best chain. A node that has followed a
+//! chain for that long stays on it, whatever work a fork shows up with later.
+//!
+//! Reorgs that do happen are logged, counted in the `civicchain_reorgs_total`
+//! Prometheus metric and sent to every `ReorgNotifications` subscriber, which
+//! is what `chain_subscribeReorgs` serves. Subscribers falling more than
+//! `MAX_PENDING_REORGS` notifications behind are dropped.
+
+use futures::channel::mpsc;
+use log::warn;
+use sc_consensus::{
+    BlockCheckParams, BlockImport, BlockImportParams, ForkChoiceStrategy, ImportResult,
+};
+use serde::{Deserialize, Serialize};
+use sp_blockchain::{Error as BlockchainError, HeaderBackend, HeaderMetadata};
+use sp_consensus::Error as ConsensusError;
+use sp_core::H256;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+use std::sync::{Arc, Mutex};
+use substrate_prometheus_endpoint::{register, Counter, PrometheusError, Registry, U64};
+
+#[cfg(test)]
+mod tests;
+
+/// Default of `--max-reorg-depth`, the depth the GHOST fork choice treats as final.
+pub const DEFAULT_MAX_REORG_DEPTH: u32 = 100;
+
+/// Notifications a subscriber may leave unread before it is dropped.
+const MAX_PENDING_REORGS: usize = 16;
+
+/// Switch of the best chain to a fork.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reorg {
+    /// Number of blocks retracted from the old best chain.
+    pub depth: u32,
+    /// Best block before the reorg.
+    pub old_head: H256,
+    /// Best block after the reorg.
+    pub new_head: H256,
+}
+
+/// Subscribers to reorgs of the best chain.
+#[derive(Clone, Default)]
+pub struct ReorgNotifications {
+    subscribers: Arc<Mutex<Vec<mpsc::Sender<Reorg>>>>,
+}
+
+impl ReorgNotifications {
+    /// Stream of the reorgs from now on. It ends if the subscriber falls too far behind.
+    pub fn subscribe(&self) -> mpsc::Receiver<Reorg> {
+        let (sender, receiver) = mpsc::channel(MAX_PENDING_REORGS);
+        self.subscribers
+            .lock()
+            .expect("Reorg subscribers lock poisoned")
+            .push(sender);
+        receiver
+    }
+
+    /// Send `reorg` to every subscriber, dropping those that went away or lag behind.
+    fn notify(&self, reorg: &Reorg) {
+        self.subscribers
+            .lock()
+            .expect("Reorg subscribers lock poisoned")
+            .retain_mut(|sender| sender.try_send(reorg.clone()).is_ok());
+    }
+}
+
+#[derive(Clone)]
+struct Metrics {
+    reorgs: Counter<U64>,
+}
+
+impl Metrics {
+    fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+        Ok(Self {
+            reorgs: register(
+                Counter::new(
+                    "civicchain_reorgs_total",
+                    "Number of reorgs of the best chain",
+                )?,
+                registry,
+            )?,
+        })
+    }
+}
+
+/// Block import refusing reorgs deeper than a limit and reporting the others.
+pub struct ReorgBlockImport<I, C> {
+    inner: I,
+    client: Arc<C>,
+    max_depth: u32,
+    notifications: ReorgNotifications,
+    metrics: Option<Metrics>,
+}
+
+impl<I, C> ReorgBlockImport<I, C> {
+    /// Wrap `inner`, which must receive blocks with their fork choice already set.
+    pub fn new(
+        inner: I,
+        client: Arc<C>,
+        max_depth: u32,
+        notifications: ReorgNotifications,
+        registry: Option<&Registry>,
+    ) -> Self {
+        let metrics = registry.and_then(|registry| {
+            Metrics::register(registry)
+                .map_err(|err| warn!(target: "reorg", "Failed to register reorg metrics: {}", err))
+                .ok()
+        });
+
+        Self {
+            inner,
+            client,
+            max_depth,
+            notifications,
+            metrics,
+        }
+    }
+}
+
+// Derive would require `C: Clone`.
+impl<I: Clone, C> Clone for ReorgBlockImport<I, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            client: self.client.clone(),
+            max_depth: self.max_depth,
+            notifications: self.notifications.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<B, I, C> BlockImport<B> for ReorgBlockImport<I, C>
+where
+    B: BlockT<Hash = H256>,
+    I: BlockImport<B> + Send,
+    I::Error: Into<ConsensusError>,
+    C: HeaderBackend<B> + HeaderMetadata<B, Error = BlockchainError> + Send + Sync,
+{
+    type Error = ConsensusError;
+    type Transaction = I::Transaction;
+
+    async fn check_block(
+        &mut self,
+        block: BlockCheckParams<B>,
+    ) -> Result<ImportResult, Self::Error> {
+        self.inner.check_block(block).await.map_err(Into::into)
+    }
+
+    async fn import_block(
+        &mut self,
+        block: BlockImportParams<B, Self::Transaction>,
+    ) -> Result<ImportResult, Self::Error> {
+        let old_head = self.client.info().best_hash;
+        let parent = *block.header.parent_hash();
+        let becomes_best = matches!(block.fork_choice, Some(ForkChoiceStrategy::Custom(true)));
+
+        // Blocks extending the best chain retract nothing
+        let depth = if becomes_best && parent != old_head {
+            let route = sp_blockchain::tree_route(&*self.client, old_head, parent)
+                .map_err(|err| ConsensusError::ChainLookup(err.to_string()))?;
+            route.retracted().len() as u32
+        } else {
+            0
+        };
+
+        if depth > self.max_depth {
+            return Err(ConsensusError::ClientImport(format!(
+                "Block {:?} would reorg {} blocks, more than the maximum of {}",
+                block.post_hash(),
+                depth,
+                self.max_depth
+            )));
+        }
+
+        let new_head = block.post_hash();
+        let result = self.inner.import_block(block).await.map_err(Into::into)?;
+
+        if depth > 0 && matches!(result, ImportResult::Imported(_)) {
+            warn!(
+                target: "reorg",
+                "Reorg of depth {} from {:?} to {:?}",
+                depth,
+                old_head,
+                new_head
+            );
+            if let Some(metrics) = &self.metrics {
+                metrics.reorgs.inc();
+            }
+            self.notifications.notify(&Reorg {
+                depth,
+                old_head,
+                new_head,
+            });
+        }
+
+        Ok(result)
+    }
+}
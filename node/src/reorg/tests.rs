@@ -0,0 +1,137 @@
+use super::*;
+use crate::test_utils::{self, TestImport};
+use civicchain_runtime::opaque::Block;
+use sc_client_api::in_mem;
+
+type TestBlockchain = in_mem::Blockchain<Block>;
+
+// Reorg import over an in-memory chain, starting at a genesis block.
+struct TestChain {
+    blockchain: TestBlockchain,
+    import: ReorgBlockImport<TestImport, TestBlockchain>,
+    notifications: ReorgNotifications,
+    genesis: H256,
+}
+
+impl TestChain {
+    fn new(max_depth: u32) -> Self {
+        let blockchain = TestBlockchain::new();
+        let genesis = test_utils::genesis(&blockchain);
+        let notifications = ReorgNotifications::default();
+        let import = ReorgBlockImport::new(
+            TestImport {
+                blockchain: blockchain.clone(),
+            },
+            Arc::new(blockchain.clone()),
+            max_depth,
+            notifications.clone(),
+            Some(&Registry::new()),
+        );
+        Self {
+            blockchain,
+            import,
+            notifications,
+            genesis,
+        }
+    }
+
+    // Import a block on top of `parent`, with the fork choice `PowBlockImport` would set.
+    async fn import(&mut self, parent: H256, salt: u8, best: bool) -> Result<H256, ConsensusError> {
+        let mut block = test_utils::block(&self.blockchain, parent, salt, 10);
+        block.fork_choice = Some(ForkChoiceStrategy::Custom(best));
+        let hash = block.post_hash();
+        self.import.import_block(block).await?;
+        Ok(hash)
+    }
+
+    // Import `length` best blocks on top of `parent` and return the tip.
+    async fn extend(&mut self, mut parent: H256, length: u32, salt: u8) -> H256 {
+        for _ in 0..length {
+            parent = self.import(parent, salt, true).await.unwrap();
+        }
+        parent
+    }
+
+    // Main chain of four blocks, and a fork off its first block ending in a block
+    // whose parent is three blocks deep. Returns the main tip and the fork tip.
+    async fn fork(&mut self) -> (H256, H256) {
+        let a1 = self.extend(self.genesis, 1, 1).await;
+        let a4 = self.extend(a1, 3, 1).await;
+        let b2 = self.import(a1, 2, false).await.unwrap();
+        let b3 = self.import(b2, 2, false).await.unwrap();
+        (a4, b3)
+    }
+
+    fn best(&self) -> H256 {
+        self.blockchain.info().best_hash
+    }
+
+    fn reorgs(&self) -> u64 {
+        self.import.metrics.as_ref().unwrap().reorgs.get()
+    }
+}
+
+#[tokio::test]
+async fn reorgs_deeper_than_the_limit_are_refused() {
+    let mut chain = TestChain::new(2);
+    let mut reorgs = chain.notifications.subscribe();
+    let (a4, b3) = chain.fork().await;
+
+    // Switching to the fork would retract three blocks
+    assert!(chain.import(b3, 2, true).await.is_err());
+    assert_eq!(chain.best(), a4);
+    assert_eq!(chain.reorgs(), 0);
+    assert!(reorgs.try_next().is_err());
+
+    // Blocks that do not become best are still imported
+    chain.import(b3, 3, false).await.unwrap();
+}
+
+#[tokio::test]
+async fn shallower_reorgs_are_reported() {
+    let mut chain = TestChain::new(3);
+    let mut reorgs = chain.notifications.subscribe();
+    let (a4, b3) = chain.fork().await;
+
+    let b4 = chain.import(b3, 2, true).await.unwrap();
+    assert_eq!(chain.best(), b4);
+    assert_eq!(chain.reorgs(), 1);
+    assert_eq!(
+        reorgs.try_next().unwrap(),
+        Some(Reorg {
+            depth: 3,
+            old_head: a4,
+            new_head: b4,
+        })
+    );
+
+    // Extending the new best chain is not a reorg
+    chain.import(b4, 2, true).await.unwrap();
+    assert_eq!(chain.reorgs(), 1);
+    assert!(reorgs.try_next().is_err());
+}
+
+#[test]
+fn lagging_subscribers_are_dropped() {
+    let notifications = ReorgNotifications::default();
+    let mut reading = notifications.subscribe();
+    let mut lagging = notifications.subscribe();
+    let reorg = Reorg {
+        depth: 1,
+        old_head: H256::repeat_byte(1),
+        new_head: H256::repeat_byte(2),
+    };
+
+    // The channel holds one message per sender on top of its buffer
+    for _ in 0..MAX_PENDING_REORGS + 2 {
+        notifications.notify(&reorg);
+        assert_eq!(reading.try_next().unwrap(), Some(reorg.clone()));
+    }
+    assert_eq!(notifications.subscribers.lock().unwrap().len(), 1);
+
+    // The lagging subscriber gets what was buffered, then its stream ends
+    for _ in 0..MAX_PENDING_REORGS + 1 {
+        assert_eq!(lagging.try_next().unwrap(), Some(reorg.clone()));
+    }
+    assert_eq!(lagging.try_next().unwrap(), None);
+}
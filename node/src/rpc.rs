@@ -7,27 +7,46 @@
 
 use std::sync::Arc;
 
-use civicchain_runtime::{opaque::Block, AccountId, Balance, Nonce};
+use crate::{
+    miner::{Hashrate, WorkSource},
+    reorg::ReorgNotifications,
+};
+use civicchain_runtime::{opaque::Block, AccountId, Balance, BlockNumber, Hash, Nonce};
 use jsonrpsee::RpcModule;
 use sc_client_api::AuxStore;
 use sc_consensus_pow::PowBlockImport;
+use sc_rpc::SubscriptionTaskExecutor;
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
+use sp_consensus_pow::PowApi;
+
+pub mod flyclient;
+pub mod mining;
+pub mod pow;
+pub mod reorg;
 
 /// Full client dependencies.
-pub struct FullDeps<C, P> {
+pub struct FullDeps<C, P, W> {
     /// The client instance to use.
     pub client: Arc<C>,
     /// Transaction pool instance.
     pub pool: Arc<P>,
     /// Whether to deny unsafe calls
     pub deny_unsafe: bool,
+    /// Mining worker, if the node mines.
+    pub worker: Option<W>,
+    /// Hashrate of the built-in miner.
+    pub hashrate: Hashrate,
+    /// Reorgs of the best chain, as reported by the block import.
+    pub reorgs: ReorgNotifications,
+    /// Executor for subscription tasks.
+    pub subscription_executor: SubscriptionTaskExecutor,
 }
 
 /// Instantiate all full RPC extensions.
-pub fn create_full<C, P>(deps: FullDeps<C, P>) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
+pub fn create_full<C, P, W>(deps: FullDeps<C, P, W>) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
 where
     C: ProvideRuntimeApi<Block>,
     C: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError> + 'static,
@@ -36,8 +55,16 @@ where
     C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Nonce>,
     C::Api: BlockBuilder<Block>,
     C::Api: pallet_contracts::ContractsRuntimeApi<Block, AccountId, Balance, BlockNumber, Hash>,
+    C::Api: PowApi<Block>,
+    C::Api: civicchain_pow::CivicPowApi<Block, AccountId, Balance>,
+    C: Send + Sync,
     P: TransactionPool + 'static,
+    W: WorkSource,
 {
+    use flyclient::{FlyClient, FlyClientApiServer};
+    use mining::{Mining, MiningApiServer};
+    use pow::{CivicPow, CivicPowApiServer};
+    use reorg::{ReorgApiServer, Reorgs};
     use pallet_contracts_rpc::{Contracts, ContractsApiServer};
     use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
     use substrate_frame_rpc_system::{System, SystemApiServer};
@@ -47,6 +74,10 @@ where
         client,
         pool,
         deny_unsafe,
+        worker,
+        hashrate,
+        reorgs,
+        subscription_executor,
     } = deps;
 
     module.merge(System::new(client.clone(), pool.clone(), deny_unsafe).into_rpc())?;
@@ -54,7 +85,10 @@ where
     module.merge(Contracts::new(client.clone()).into_rpc())?;
 
     // Add custom RPCs for CivicChain
-    // ...
+    module.merge(CivicPow::new(client.clone()).into_rpc())?;
+    module.merge(FlyClient::new(client.clone()).into_rpc())?;
+    module.merge(Mining::new(client, worker, hashrate).into_rpc())?;
+    module.merge(Reorgs::new(reorgs, subscription_executor).into_rpc())?;
 
     Ok(module)
 }
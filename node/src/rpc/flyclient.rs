@@ -0,0 +1,83 @@
+//! `flyclient_*` RPC methods serving FlyClient proofs to light clients.
+
+use std::sync::Arc;
+
+use crate::flyclient::MmrStore;
+use civicchain_pow::CivicPowApi as CivicPowRuntimeApi;
+use civicchain_runtime::{opaque::Block, AccountId, Balance, Hash};
+use jsonrpsee::{
+    core::RpcResult,
+    proc_macros::rpc,
+    types::error::{CallError, ErrorObject},
+};
+use parity_scale_codec::Encode;
+use sc_client_api::AuxStore;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::Bytes;
+
+/// The proof could not be built.
+const PROOF_ERROR: i32 = 1;
+/// The runtime could not be queried.
+const RUNTIME_ERROR: i32 = 2;
+
+/// FlyClient RPC methods.
+#[rpc(client, server)]
+pub trait FlyClientApi {
+    /// SCALE-encoded `FlyClientProof` for block `head` (the best block by
+    /// default), with `security_param` blocks sampled by difficulty.
+    ///
+    /// `head` must be on the best chain. The proof is checked with
+    /// `civicchain_primitives::flyclient::verify`.
+    #[method(name = "flyclient_getProof")]
+    fn get_proof(&self, head: Option<Hash>, security_param: u32) -> RpcResult<Bytes>;
+}
+
+/// Implementation of [`FlyClientApiServer`] backed by the MMR aux store.
+pub struct FlyClient<C> {
+    client: Arc<C>,
+    store: MmrStore<Block, C>,
+}
+
+impl<C> FlyClient<C> {
+    /// Create a new instance.
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            store: MmrStore::new(client.clone()),
+            client,
+        }
+    }
+}
+
+impl<C> FlyClientApiServer for FlyClient<C>
+where
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore + Send + Sync + 'static,
+    C::Api: CivicPowRuntimeApi<Block, AccountId, Balance>,
+{
+    fn get_proof(&self, head: Option<Hash>, security_param: u32) -> RpcResult<Bytes> {
+        let head = head.unwrap_or_else(|| self.client.info().best_hash);
+        let schedule = self
+            .client
+            .runtime_api()
+            .pow_schedule(head)
+            .map_err(|err| {
+                CallError::Custom(ErrorObject::owned(
+                    RUNTIME_ERROR,
+                    "Unable to query work function parameters",
+                    Some(format!("{:?}", err)),
+                ))
+            })?;
+
+        self.store
+            .proof(head, security_param, schedule)
+            .map(|proof| proof.encode().into())
+            .map_err(|err| {
+                CallError::Custom(ErrorObject::owned(
+                    PROOF_ERROR,
+                    "Unable to build FlyClient proof",
+                    Some(err.to_string()),
+                ))
+                .into()
+            })
+    }
+}
@@ -0,0 +1,222 @@
+//! `mining_*` RPC methods for pool software and external miners.
+//!
+//! Work is taken from the node's mining worker, so these methods are only
+//! useful on nodes started with `--mine`, `--stratum-listen` or `--validator`.
+
+use std::sync::Arc;
+
+use crate::miner::{Hashrate, WorkSource};
+use civicchain_pow::{CivicPowApi as CivicPowRuntimeApi, CivicSeal, Nonce, NONCE_SIZE};
+use civicchain_runtime::{opaque::Block, AccountId, Balance};
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+    types::error::{CallError, ErrorObject},
+};
+use parity_scale_codec::Encode;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_consensus_pow::PowApi;
+use sp_core::{Bytes, H256, U256};
+
+/// The node is not running a mining worker.
+const MINING_DISABLED: i32 = 1;
+/// The runtime could not be queried.
+const RUNTIME_ERROR: i32 = 2;
+/// The nonce does not have the width of a seal nonce.
+const INVALID_NONCE: i32 = 3;
+
+/// Work for the block currently being mined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MiningWork {
+    /// Hash the seal must commit to.
+    pub pow_hash: H256,
+    /// Difficulty the seal must meet.
+    pub difficulty: U256,
+    /// Largest work hash meeting `difficulty`.
+    pub target: U256,
+    /// Bytes hashed ahead of `powHash` and the nonce, set by the work function
+    /// parameters in force.
+    pub personalization: Bytes,
+}
+
+/// Mining status of the node and the network.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MiningInfo {
+    /// Whether the node is running a mining worker.
+    pub mining: bool,
+    /// Pre-hash of the block being mined, if any.
+    pub pow_hash: Option<H256>,
+    /// Difficulty of the next block on top of the best block.
+    pub difficulty: U256,
+    /// Largest work hash meeting `difficulty`.
+    pub target: U256,
+    /// Hashes per second computed by the built-in miner.
+    pub hashrate: u64,
+    /// Estimated hashes per second of the whole network.
+    pub network_hashrate: U256,
+}
+
+/// Mining RPC methods.
+#[rpc(client, server)]
+pub trait MiningApi {
+    /// Work for the block currently being mined.
+    #[method(name = "mining_getWork")]
+    fn get_work(&self) -> RpcResult<MiningWork>;
+
+    /// Submit a nonce and its Yespower `digest` for the work identified by `pow_hash`.
+    /// The nonce must be exactly `NONCE_SIZE` bytes.
+    ///
+    /// Returns whether the resulting block was imported.
+    #[method(name = "mining_submitWork")]
+    async fn submit_work(&self, nonce: Bytes, pow_hash: H256, digest: H256) -> RpcResult<bool>;
+
+    /// Hashes per second computed by the built-in miner.
+    #[method(name = "mining_getHashrate")]
+    fn get_hashrate(&self) -> RpcResult<u64>;
+
+    /// Mining status of the node and the network.
+    #[method(name = "mining_getMiningInfo")]
+    fn get_mining_info(&self) -> RpcResult<MiningInfo>;
+}
+
+/// Implementation of [`MiningApiServer`] backed by the mining worker.
+pub struct Mining<C, W> {
+    client: Arc<C>,
+    worker: Option<W>,
+    hashrate: Hashrate,
+}
+
+impl<C, W> Mining<C, W> {
+    /// Create a new instance. `worker` is `None` when the node does not mine.
+    pub fn new(client: Arc<C>, worker: Option<W>, hashrate: Hashrate) -> Self {
+        Self {
+            client,
+            worker,
+            hashrate,
+        }
+    }
+}
+
+impl<C, W> Mining<C, W>
+where
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+    C::Api: PowApi<Block> + CivicPowRuntimeApi<Block, AccountId, Balance>,
+    W: WorkSource,
+{
+    fn worker(&self) -> RpcResult<&W> {
+        self.worker
+            .as_ref()
+            .ok_or_else(|| error(MINING_DISABLED, "Node is not mining", None::<()>))
+    }
+
+    fn best_difficulty(&self) -> RpcResult<U256> {
+        let best = self.client.info().best_hash;
+        self.client.runtime_api().difficulty(best).map_err(|err| {
+            error(
+                RUNTIME_ERROR,
+                "Unable to query difficulty",
+                Some(format!("{:?}", err)),
+            )
+        })
+    }
+
+    fn target_block_time(&self) -> RpcResult<u64> {
+        let best = self.client.info().best_hash;
+        self.client
+            .runtime_api()
+            .target_block_time(best)
+            .map_err(|err| {
+                error(
+                    RUNTIME_ERROR,
+                    "Unable to query target block time",
+                    Some(format!("{:?}", err)),
+                )
+            })
+    }
+}
+
+#[async_trait]
+impl<C, W> MiningApiServer for Mining<C, W>
+where
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+    C::Api: PowApi<Block> + CivicPowRuntimeApi<Block, AccountId, Balance>,
+    W: WorkSource,
+{
+    fn get_work(&self) -> RpcResult<MiningWork> {
+        let work = self
+            .worker()?
+            .work()
+            .ok_or_else(|| error(MINING_DISABLED, "No block is being mined yet", None::<()>))?;
+
+        Ok(MiningWork {
+            pow_hash: work.pre_hash,
+            difficulty: work.difficulty,
+            target: target(work.difficulty),
+            personalization: work.params.personalization.into(),
+        })
+    }
+
+    async fn submit_work(&self, nonce: Bytes, pow_hash: H256, digest: H256) -> RpcResult<bool> {
+        let worker = self.worker()?;
+        let nonce = Nonce::try_from(&nonce[..]).map_err(|_| {
+            error(
+                INVALID_NONCE,
+                &format!("Nonce must be {} bytes", NONCE_SIZE),
+                None::<()>,
+            )
+        })?;
+
+        // Solutions for a previous build can no longer be imported.
+        let work = match worker.work() {
+            Some(work) if work.pre_hash == pow_hash => work,
+            _ => return Ok(false),
+        };
+
+        let seal = CivicSeal::new(work.difficulty, nonce, work.poh, digest);
+        if !seal.verify(&work.params, pow_hash.as_bytes()) {
+            return Ok(false);
+        }
+
+        Ok(worker.submit(seal.encode()).await)
+    }
+
+    fn get_hashrate(&self) -> RpcResult<u64> {
+        Ok(self.hashrate.hashes_per_second())
+    }
+
+    fn get_mining_info(&self) -> RpcResult<MiningInfo> {
+        let work = self.worker.as_ref().and_then(WorkSource::work);
+        let difficulty = match &work {
+            Some(work) => work.difficulty,
+            None => self.best_difficulty()?,
+        };
+
+        Ok(MiningInfo {
+            mining: self.worker.is_some(),
+            pow_hash: work.map(|work| work.pre_hash),
+            difficulty,
+            target: target(difficulty),
+            hashrate: self.hashrate.hashes_per_second(),
+            network_hashrate: network_hashrate(difficulty, self.target_block_time()?),
+        })
+    }
+}
+
+/// Largest work hash meeting `difficulty`.
+fn target(difficulty: U256) -> U256 {
+    U256::MAX / difficulty.max(U256::one())
+}
+
+/// A block takes `difficulty` hashes on average and the network produces one
+/// every `target_block_time` milliseconds, which retargeting keeps roughly true.
+fn network_hashrate(difficulty: U256, target_block_time: u64) -> U256 {
+    difficulty.saturating_mul(U256::from(1_000)) / U256::from(target_block_time.max(1))
+}
+
+fn error(code: i32, message: &str, data: Option<impl Serialize>) -> jsonrpsee::core::Error {
+    CallError::Custom(ErrorObject::owned(code, message, data)).into()
+}
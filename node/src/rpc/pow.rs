@@ -0,0 +1,56 @@
+//! `pow_*` RPC methods exposing PoW pallet state, such as block authors.
+
+use std::sync::Arc;
+
+use civicchain_pow::CivicPowApi as CivicPowRuntimeApi;
+use civicchain_runtime::{opaque::Block, AccountId, Balance, Hash};
+use jsonrpsee::{
+    core::RpcResult,
+    proc_macros::rpc,
+    types::error::{CallError, ErrorObject},
+};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+
+/// The runtime could not be queried.
+const RUNTIME_ERROR: i32 = 1;
+
+/// PoW RPC methods.
+#[rpc(client, server)]
+pub trait CivicPowApi {
+    /// Account that mined block `at` (the best block by default), if the block
+    /// carried an author digest.
+    #[method(name = "pow_getBlockAuthor")]
+    fn block_author(&self, at: Option<Hash>) -> RpcResult<Option<AccountId>>;
+}
+
+/// Implementation of [`CivicPowApiServer`] backed by the runtime API.
+pub struct CivicPow<C> {
+    client: Arc<C>,
+}
+
+impl<C> CivicPow<C> {
+    /// Create a new instance.
+    pub fn new(client: Arc<C>) -> Self {
+        Self { client }
+    }
+}
+
+impl<C> CivicPowApiServer for CivicPow<C>
+where
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+    C::Api: CivicPowRuntimeApi<Block, AccountId, Balance>,
+{
+    fn block_author(&self, at: Option<Hash>) -> RpcResult<Option<AccountId>> {
+        let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+        self.client.runtime_api().author(at).map_err(|err| {
+            CallError::Custom(ErrorObject::owned(
+                RUNTIME_ERROR,
+                "Unable to query block author",
+                Some(format!("{:?}", err)),
+            ))
+            .into()
+        })
+    }
+}
@@ -0,0 +1,49 @@
+//! `chain_subscribeReorgs` subscription, for services that need to know when
+//! blocks they acted on leave the best chain.
+
+use crate::reorg::{Reorg, ReorgNotifications};
+use futures::FutureExt;
+use jsonrpsee::{core::SubscriptionResult, proc_macros::rpc, SubscriptionSink};
+use sc_rpc::SubscriptionTaskExecutor;
+
+/// Reorg RPC methods.
+#[rpc(client, server)]
+pub trait ReorgApi {
+    /// Every reorg of the best chain from now on, with its depth and the best
+    /// block before and after it.
+    #[subscription(
+        name = "chain_subscribeReorgs" => "chain_reorg",
+        unsubscribe = "chain_unsubscribeReorgs",
+        item = Reorg
+    )]
+    fn subscribe_reorgs(&self);
+}
+
+/// Implementation of [`ReorgApiServer`] backed by the block import's notifications.
+pub struct Reorgs {
+    notifications: ReorgNotifications,
+    executor: SubscriptionTaskExecutor,
+}
+
+impl Reorgs {
+    /// Create a new instance.
+    pub fn new(notifications: ReorgNotifications, executor: SubscriptionTaskExecutor) -> Self {
+        Self {
+            notifications,
+            executor,
+        }
+    }
+}
+
+impl ReorgApiServer for Reorgs {
+    fn subscribe_reorgs(&self, mut sink: SubscriptionSink) -> SubscriptionResult {
+        let stream = self.notifications.subscribe();
+        let fut = async move {
+            sink.pipe_from_stream(stream).await;
+        };
+
+        self.executor
+            .spawn("civicchain-rpc-subscription", Some("rpc"), fut.boxed());
+        Ok(())
+    }
+}
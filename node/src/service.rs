@@ -1,14 +1,24 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
+use crate::{
+    chain_spec::Extensions,
+    checkpoint::CheckpointBlockImport,
+    cli::MiningParams,
+    ghost::{ForkChoice, GhostBlockImport, GhostSelectChain},
+    miner::{Hashrate, PowWorker},
+    poh::{PohBlockImport, PohClock, PohInherentDataProvider},
+    pow::YespowerAlgorithm,
+    reorg::{ReorgBlockImport, ReorgNotifications},
+};
 use civicchain_runtime::{self, opaque::Block, RuntimeApi};
 use sc_client_api::BlockBackend;
-use sc_consensus_pow::{PowBlockImport, PowParams};
+use parity_scale_codec::Encode;
+use sc_consensus_pow::PowBlockImport;
 use sc_executor::NativeElseWasmExecutor;
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
 use sc_telemetry::{Telemetry, TelemetryWorker};
 use sp_consensus_pow::Seal as PowSeal;
-use sp_core::U256;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 // Our native executor instance.
 pub struct ExecutorDispatch;
@@ -33,10 +43,12 @@ impl sc_executor::NativeExecutionDispatch for ExecutorDispatch {
 pub(crate) type FullClient =
     sc_service::TFullClient<Block, RuntimeApi, NativeElseWasmExecutor<ExecutorDispatch>>;
 type FullBackend = sc_service::TFullBackend<Block>;
-type FullSelectChain = sc_consensus::LongestChain<FullBackend, Block>;
+type FullSelectChain = GhostSelectChain<Block, FullBackend>;
 
 pub fn new_partial(
     config: &Configuration,
+    fork_choice: ForkChoice,
+    max_reorg_depth: u32,
 ) -> Result<
     sc_service::PartialComponents<
         FullClient,
@@ -45,14 +57,24 @@ pub fn new_partial(
         sc_consensus::DefaultImportQueue<Block, FullClient>,
         sc_transaction_pool::FullPool<Block, FullClient>,
         (
-            PowBlockImport<
-                Block,
-                Arc<FullClient>,
+            CheckpointBlockImport<
+                PohBlockImport<
+                    GhostBlockImport<
+                        Block,
+                        PowBlockImport<
+                            Block,
+                            ReorgBlockImport<Arc<FullClient>, FullClient>,
+                            FullClient,
+                            FullSelectChain,
+                            YespowerAlgorithm<FullClient>,
+                            impl sp_consensus::CanAuthorWith<Block>,
+                        >,
+                        FullBackend,
+                    >,
+                >,
                 FullClient,
-                FullSelectChain,
-                civicchain_pow::PowPallet<civicchain_runtime::Runtime>,
-                impl sp_consensus::CanAuthorWith<Block>,
             >,
+            ReorgNotifications,
             Option<Telemetry>,
         ),
     >,
@@ -91,7 +113,7 @@ pub fn new_partial(
         telemetry
     });
 
-    let select_chain = sc_consensus::LongestChain::new(backend.clone());
+    let select_chain = GhostSelectChain::new(backend.clone(), fork_choice);
 
     let transaction_pool = sc_transaction_pool::BasicPool::new_full(
         config.transaction_pool.clone(),
@@ -103,24 +125,54 @@ pub fn new_partial(
 
     let can_author_with = sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone());
 
-    let pow_block_import = PowBlockImport::new(
+    // Refuses reorgs deeper than `max_reorg_depth` and reports the others. It sits
+    // below `PowBlockImport`, which settles the fork choice of every block
+    let reorgs = ReorgNotifications::default();
+    let reorg_block_import = ReorgBlockImport::new(
         client.clone(),
         client.clone(),
-        civicchain_pow::PowPallet::new(client.clone()),
+        max_reorg_depth,
+        reorgs.clone(),
+        config.prometheus_registry(),
+    );
+
+    let pow_block_import = PowBlockImport::new(
+        reorg_block_import,
+        client.clone(),
+        YespowerAlgorithm::new(client.clone()),
         0, // PoW engine ID
         select_chain.clone(),
+        // Difficulty itself is queried from the runtime at the parent block by
+        // `YespowerAlgorithm`, so only the inherents need to be provided here. The
+        // timestamp inherent is checked against the local clock on import, and
+        // `pallet_timestamp` rejects blocks timestamped more than 30 seconds ahead of it.
         move |_, ()| async move {
-            let difficulty = U256::from(1_000_000);
-            Ok(difficulty)
+            let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
+            Ok(timestamp)
         },
         can_author_with,
     );
+    // Sets the fork choice of imported blocks before their seal is checked
+    let pow_block_import = GhostBlockImport::new(pow_block_import, select_chain.clone());
+    // Rejects blocks whose Proof-of-History sequence does not follow from their parent
+    let pow_block_import = PohBlockImport::new(pow_block_import);
+    // Rejects blocks contradicting the chain spec checkpoints, and keeps chains below the
+    // minimum total difficulty from becoming the best chain
+    let extensions = Extensions::try_get(&*config.chain_spec)
+        .cloned()
+        .unwrap_or_default();
+    let pow_block_import = CheckpointBlockImport::new(
+        pow_block_import,
+        client.clone(),
+        extensions.checkpoints,
+        extensions.min_total_difficulty,
+    );
 
     let import_queue = sc_consensus_pow::import_queue(
         Box::new(pow_block_import.clone()),
         None,
         None,
-        civicchain_pow::PowPallet::new(client.clone()),
+        YespowerAlgorithm::new(client.clone()),
         &task_manager.spawn_essential_handle(),
         config.prometheus_registry(),
     )?;
@@ -133,12 +185,17 @@ pub fn new_partial(
         keystore_container,
         select_chain,
         transaction_pool,
-        other: (pow_block_import, telemetry),
+        other: (pow_block_import, reorgs, telemetry),
     })
 }
 
 /// Builds a new service for a full client.
-pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
+pub fn new_full(
+    config: Configuration,
+    fork_choice: ForkChoice,
+    max_reorg_depth: u32,
+    mining: MiningParams,
+) -> Result<TaskManager, ServiceError> {
     let sc_service::PartialComponents {
         client,
         backend,
@@ -147,8 +204,8 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
         keystore_container,
         select_chain,
         transaction_pool,
-        other: (pow_block_import, mut telemetry),
-    } = new_partial(&config)?;
+        other: (pow_block_import, reorgs, mut telemetry),
+    } = new_partial(&config, fork_choice, max_reorg_depth)?;
 
     let (network, system_rpc_tx, tx_handler_controller, network_starter) =
         sc_service::build_network(sc_service::BuildNetworkParams {
@@ -172,16 +229,85 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 
     let role = config.role.clone();
     let prometheus_registry = config.prometheus_registry().cloned();
+    let hashrate = Hashrate::default();
+
+    // The mining worker is created before the RPC server so `mining_*` methods can use it.
+    let worker = if role.is_authority() || mining.is_mining() {
+        let proposer = sc_basic_authorship::ProposerFactory::new(
+            task_manager.spawn_handle(),
+            client.clone(),
+            transaction_pool.clone(),
+            prometheus_registry.as_ref(),
+            telemetry.as_ref().map(|x| x.handle()),
+        );
+
+        // Ticks the Proof-of-History sequence of the best block until we build on it
+        let poh_clock = PohClock::start::<Block, _>(client.clone());
+        let issued_poh = poh_clock.clone();
+
+        let (worker, worker_task) = sc_consensus_pow::start_mining_worker(
+            Box::new(pow_block_import),
+            client.clone(),
+            select_chain,
+            YespowerAlgorithm::new(client.clone()),
+            proposer,
+            network.clone(),
+            network.clone(),
+            // The author is carried in a pre-runtime digest of every block we build
+            mining.mining_author.as_ref().map(|author| author.encode()),
+            move |parent, ()| {
+                let poh = PohInherentDataProvider(poh_clock.sequence(parent));
+                async move {
+                    let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
+                    Ok((timestamp, poh))
+                }
+            },
+            // Time to wait for a new block before starting to mine a new one
+            Duration::from_secs(10),
+            // How long to take to actually build the block (i.e. executing extrinsics)
+            Duration::from_secs(10),
+        );
+
+        task_manager
+            .spawn_essential_handle()
+            .spawn_blocking("pow", Some("block-authoring"), worker_task);
+
+        // Seals refer to the PoH sequence handed to the build they are mined for, and
+        // are hashed with the work function parameters of its height
+        let algorithm = YespowerAlgorithm::new(client.clone());
+        let worker = PowWorker::new(worker, issued_poh, algorithm);
+
+        #[cfg(feature = "stratum")]
+        if let Some(listen) = mining.stratum_listen {
+            let config = crate::stratum::StratumConfig {
+                listen,
+                share_difficulty: sp_core::U256::from(mining.stratum_share_difficulty),
+            };
+            crate::stratum::start(worker.clone(), config, task_manager.spawn_handle());
+        }
+
+        if mining.mine {
+            crate::miner::start(worker.clone(), mining.mining_threads, hashrate.clone());
+        }
+
+        Some(worker)
+    } else {
+        None
+    };
 
     let rpc_extensions_builder = {
         let client = client.clone();
         let pool = transaction_pool.clone();
 
-        Box::new(move |deny_unsafe, _| {
+        Box::new(move |deny_unsafe, subscription_executor| {
             let deps = crate::rpc::FullDeps {
                 client: client.clone(),
                 pool: pool.clone(),
                 deny_unsafe,
+                worker: worker.clone(),
+                hashrate: hashrate.clone(),
+                reorgs: reorgs.clone(),
+                subscription_executor,
             };
 
             Ok(crate::rpc::create_full(deps))
@@ -202,40 +328,6 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
         telemetry: telemetry.as_mut(),
     })?;
 
-    if role.is_authority() {
-        let proposer = sc_basic_authorship::ProposerFactory::new(
-            task_manager.spawn_handle(),
-            client.clone(),
-            transaction_pool,
-            prometheus_registry.as_ref(),
-            telemetry.as_ref().map(|x| x.handle()),
-        );
-
-        let can_author_with = sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone());
-
-        let pow_params = PowParams {
-            client: client.clone(),
-            env: proposer,
-            select_chain,
-            block_import: pow_block_import,
-            sync_oracle: network.clone(),
-            justification_sync_link: network.clone(),
-            create_inherent_data_providers: move |_, ()| async move {
-                Ok(sp_timestamp::InherentDataProvider::from_system_time())
-            },
-            force_authoring: false,
-            backoff_authoring_blocks: Some(sc_consensus_slots::BackoffAuthoringOnFinalizedHeadLagging::default()),
-            pow_algorithm: civicchain_pow::PowPallet::new(client.clone()),
-            can_author_with,
-        };
-
-        let authorship_future = sc_consensus_pow::start_mining_worker(pow_params);
-
-        task_manager
-            .spawn_essential_handle()
-            .spawn_blocking("pow", None, authorship_future);
-    }
-
     network_starter.start_network();
     Ok(task_manager)
 }
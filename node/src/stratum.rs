@@ -0,0 +1,412 @@
+//! Stratum V1 server for external and mobile miners.
+//!
+//! Only built with the `stratum` cargo feature. Messages use the usual Stratum V1
+//! framing (one JSON-RPC object per line) with Yespower-R16 specific job contents:
+//!
+//! * `mining.subscribe` → `[[["mining.notify", <subscription>]], <extranonce1>, 4]`
+//! * `mining.authorize` → `true`
+//! * `mining.set_difficulty` (server) → `[<share difficulty>]`
+//! * `mining.notify` (server) → `[<job id>, <pre-hash>, <network difficulty>, <clean jobs>, <personalization>]`
+//! * `mining.submit` `[<worker>, <job id>, <nonce>]` → `true` if the share is accepted
+//!
+//! The seal nonce is the 4-byte `extranonce1` assigned at subscription followed by
+//! the miner's 4-byte nonce, so two connected miners never search the same space.
+//! Lines longer than `MAX_LINE_LENGTH` close the connection, and each connection may
+//! submit at most `MAX_SHARES_PER_WINDOW` shares every `SHARE_RATE_WINDOW`.
+//! Miners hash the hex `personalization` of the job, which changes when governance
+//! switches the work function parameters, ahead of the pre-hash and nonce.
+//! Shares meeting the network difficulty are submitted to the mining worker as seals.
+
+use crate::miner::{Work, WorkSource};
+use civicchain_pow::{compute_work, meets_difficulty, CivicSeal, Nonce, NONCE_SIZE};
+use futures::{future::BoxFuture, FutureExt};
+use log::{debug, info, warn};
+use parity_scale_codec::Encode;
+use parking_lot::Mutex;
+use sc_service::SpawnTaskHandle;
+use serde_json::{json, Value};
+use sp_core::U256;
+use std::{
+    collections::{HashSet, VecDeque},
+    io,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{tcp::OwnedWriteHalf, TcpListener, TcpStream},
+    sync::broadcast,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Size of the per-connection nonce prefix assigned by the server.
+const EXTRANONCE1_SIZE: usize = 4;
+
+/// Size of the nonce searched by the miner, which fills the rest of the seal nonce.
+const EXTRANONCE2_SIZE: usize = NONCE_SIZE - EXTRANONCE1_SIZE;
+
+/// How often the mining worker is polled for a new build.
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Number of recent jobs that shares may still be submitted against.
+const MAX_JOBS: usize = 8;
+
+/// Longest message a miner may send, in bytes, newline included.
+const MAX_LINE_LENGTH: usize = 4 * 1024;
+
+/// Window over which share submissions are counted.
+const SHARE_RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Shares a connection may submit per `SHARE_RATE_WINDOW`. Each one costs a
+/// Yespower hash, so miners are expected to pick a share difficulty well below it.
+const MAX_SHARES_PER_WINDOW: u32 = 50;
+
+// Stratum V1 error codes.
+const ERROR_OTHER: i64 = 20;
+const ERROR_JOB_NOT_FOUND: i64 = 21;
+const ERROR_DUPLICATE_SHARE: i64 = 22;
+const ERROR_LOW_DIFFICULTY: i64 = 23;
+const ERROR_NOT_SUBSCRIBED: i64 = 25;
+const ERROR_TOO_MANY_SHARES: i64 = 26;
+
+/// Stratum server configuration.
+#[derive(Debug, Clone)]
+pub struct StratumConfig {
+    /// Address to listen on.
+    pub listen: SocketAddr,
+    /// Difficulty a share must meet to be accepted.
+    pub share_difficulty: U256,
+}
+
+#[derive(Debug, Clone)]
+struct Job {
+    id: String,
+    work: Work,
+}
+
+#[derive(Default)]
+struct Jobs(Mutex<VecDeque<Job>>);
+
+impl Jobs {
+    fn push(&self, job: Job) {
+        let mut jobs = self.0.lock();
+        jobs.push_front(job);
+        jobs.truncate(MAX_JOBS);
+    }
+
+    fn get(&self, id: &str) -> Option<Job> {
+        self.0.lock().iter().find(|job| job.id == id).cloned()
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.0.lock().iter().any(|job| job.id == id)
+    }
+
+    fn latest(&self) -> Option<Job> {
+        self.0.lock().front().cloned()
+    }
+}
+
+/// Start the Stratum server, handing out work from `worker`.
+pub fn start<W: WorkSource>(worker: W, config: StratumConfig, spawn_handle: SpawnTaskHandle) {
+    let connections = spawn_handle.clone();
+
+    spawn_handle.spawn("stratum", Some("mining"), async move {
+        let listener = match TcpListener::bind(config.listen).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                warn!(target: "stratum", "Failed to bind Stratum server to {}: {}", config.listen, err);
+                return;
+            }
+        };
+        info!(target: "stratum", "⛏  Stratum server listening on {}", config.listen);
+
+        let spawn = move |task| connections.spawn("stratum-connection", Some("mining"), task);
+        if let Err(err) = serve(listener, worker, config.share_difficulty, spawn).await {
+            warn!(target: "stratum", "Stratum server stopped: {}", err);
+        }
+    });
+}
+
+/// Accept miners on `listener` until it fails, spawning one task per connection.
+async fn serve<W, S>(
+    listener: TcpListener,
+    worker: W,
+    share_difficulty: U256,
+    spawn: S,
+) -> io::Result<()>
+where
+    W: WorkSource,
+    S: Fn(BoxFuture<'static, ()>),
+{
+    let jobs = Arc::new(Jobs::default());
+    let (notify, _) = broadcast::channel(16);
+
+    spawn(poll_jobs(worker.clone(), jobs.clone(), notify.clone()).boxed());
+
+    // Counting up hands every connection its own nonce space, until it wraps around
+    // after 2^32 connections.
+    let mut next_extranonce1: u32 = 0;
+
+    loop {
+        let (socket, peer) = listener.accept().await?;
+        debug!(target: "stratum", "Miner connected from {}", peer);
+
+        let extranonce1 = next_extranonce1.to_be_bytes();
+        next_extranonce1 = next_extranonce1.wrapping_add(1);
+
+        let session = Session {
+            worker: worker.clone(),
+            jobs: jobs.clone(),
+            share_difficulty,
+            extranonce1,
+            subscribed: false,
+            submitted: HashSet::new(),
+            window_start: Instant::now(),
+            window_shares: 0,
+        };
+        let notifications = notify.subscribe();
+
+        spawn(
+            async move {
+                if let Err(err) = session.run(socket, notifications).await {
+                    debug!(target: "stratum", "Miner {} disconnected: {}", peer, err);
+                }
+            }
+            .boxed(),
+        );
+    }
+}
+
+/// Turn every new build of the mining worker into a job for connected miners.
+async fn poll_jobs<W: WorkSource>(worker: W, jobs: Arc<Jobs>, notify: broadcast::Sender<Job>) {
+    let mut last_version = None;
+
+    loop {
+        if let Some(work) = worker.work() {
+            if last_version != Some(work.version) {
+                last_version = Some(work.version);
+
+                let job = Job {
+                    id: format!("{:x}", work.version),
+                    work,
+                };
+                jobs.push(job.clone());
+                // Sending only fails when no miner is connected.
+                let _ = notify.send(job);
+            }
+        }
+
+        tokio::time::sleep(JOB_POLL_INTERVAL).await;
+    }
+}
+
+struct Session<W> {
+    worker: W,
+    jobs: Arc<Jobs>,
+    share_difficulty: U256,
+    extranonce1: [u8; EXTRANONCE1_SIZE],
+    subscribed: bool,
+    submitted: HashSet<(String, Nonce)>,
+    /// Start of the current share rate window, and shares submitted within it.
+    window_start: Instant,
+    window_shares: u32,
+}
+
+impl<W: WorkSource> Session<W> {
+    async fn run(
+        mut self,
+        socket: TcpStream,
+        mut notifications: broadcast::Receiver<Job>,
+    ) -> io::Result<()> {
+        let (reader, mut writer) = socket.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+
+        loop {
+            tokio::select! {
+                line = read_line(&mut reader, &mut buf) => {
+                    let line = match line? {
+                        Some(line) => line,
+                        None => return Ok(()),
+                    };
+
+                    for message in self.handle(&line).await {
+                        write_message(&mut writer, &message).await?;
+                    }
+                }
+                job = notifications.recv() => match job {
+                    Ok(job) if self.subscribed => {
+                        // Shares are accepted for the last `MAX_JOBS` jobs, so only forget
+                        // those of jobs that dropped out.
+                        let jobs = &self.jobs;
+                        self.submitted.retain(|(job_id, _)| jobs.contains(job_id));
+                        write_message(&mut writer, &self.set_difficulty(&job)).await?;
+                        write_message(&mut writer, &notify_message(&job)).await?;
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                },
+            }
+        }
+    }
+
+    async fn handle(&mut self, line: &str) -> Vec<Value> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(_) => return vec![error_response(Value::Null, ERROR_OTHER, "Invalid JSON")],
+        };
+        let id = request["id"].clone();
+        let params = request["params"].as_array().cloned().unwrap_or_default();
+
+        match request["method"].as_str() {
+            Some("mining.subscribe") => {
+                self.subscribed = true;
+                let extranonce1 = hex::encode(self.extranonce1);
+
+                let mut replies = vec![response(
+                    id,
+                    json!([[["mining.notify", extranonce1]], extranonce1, EXTRANONCE2_SIZE]),
+                )];
+                if let Some(job) = self.jobs.latest() {
+                    replies.push(self.set_difficulty(&job));
+                    replies.push(notify_message(&job));
+                }
+                replies
+            }
+            // Rewards go to the node's mining author, so any worker name is accepted.
+            Some("mining.authorize") => vec![response(id, json!(true))],
+            Some("mining.submit") => vec![self.submit(id, &params).await],
+            _ => vec![error_response(id, ERROR_OTHER, "Unknown method")],
+        }
+    }
+
+    async fn submit(&mut self, id: Value, params: &[Value]) -> Value {
+        if !self.subscribed {
+            return error_response(id, ERROR_NOT_SUBSCRIBED, "Not subscribed");
+        }
+        if !self.count_share() {
+            return error_response(id, ERROR_TOO_MANY_SHARES, "Too many shares");
+        }
+
+        let (job_id, nonce) = match (
+            params.get(1).and_then(Value::as_str),
+            params.get(2).and_then(Value::as_str),
+        ) {
+            (Some(job_id), Some(nonce)) => (job_id, nonce),
+            _ => return error_response(id, ERROR_OTHER, "Invalid params"),
+        };
+
+        let job = match self.jobs.get(job_id) {
+            Some(job) => job,
+            None => return error_response(id, ERROR_JOB_NOT_FOUND, "Job not found"),
+        };
+
+        let extranonce2 = match hex::decode(nonce) {
+            Ok(extranonce2) if extranonce2.len() == EXTRANONCE2_SIZE => extranonce2,
+            _ => return error_response(id, ERROR_OTHER, "Invalid nonce"),
+        };
+        let mut nonce = Nonce::default();
+        nonce[..EXTRANONCE1_SIZE].copy_from_slice(&self.extranonce1);
+        nonce[EXTRANONCE1_SIZE..].copy_from_slice(&extranonce2);
+
+        if !self.submitted.insert((job.id.clone(), nonce)) {
+            return error_response(id, ERROR_DUPLICATE_SHARE, "Duplicate share");
+        }
+
+        let work = compute_work(&job.work.params, job.work.pre_hash.as_bytes(), &nonce);
+        if !meets_difficulty(&work, self.share_difficulty(&job)) {
+            return error_response(id, ERROR_LOW_DIFFICULTY, "Low difficulty share");
+        }
+
+        if meets_difficulty(&work, job.work.difficulty) {
+            let seal = CivicSeal::new(job.work.difficulty, nonce, job.work.poh, work);
+
+            if self.worker.submit(seal.encode()).await {
+                info!(target: "stratum", "⛏  Block found by Stratum miner on job {}", job.id);
+            } else {
+                debug!(target: "stratum", "Solution for stale job {} was not accepted", job.id);
+            }
+        }
+
+        response(id, json!(true))
+    }
+
+    // Count a submission against the rate limit, returning whether it is allowed.
+    // Rejected submissions count too, so they cannot be retried in a loop.
+    fn count_share(&mut self) -> bool {
+        let now = Instant::now();
+        if now.duration_since(self.window_start) >= SHARE_RATE_WINDOW {
+            self.window_start = now;
+            self.window_shares = 0;
+        }
+
+        self.window_shares = self.window_shares.saturating_add(1);
+        self.window_shares <= MAX_SHARES_PER_WINDOW
+    }
+
+    // Shares are never harder than the block itself.
+    fn share_difficulty(&self, job: &Job) -> U256 {
+        self.share_difficulty.min(job.work.difficulty)
+    }
+
+    fn set_difficulty(&self, job: &Job) -> Value {
+        let difficulty = self.share_difficulty(job).min(U256::from(u64::MAX)).low_u64();
+        json!({ "id": null, "method": "mining.set_difficulty", "params": [difficulty] })
+    }
+}
+
+/// Read the next line from `reader`, without its line ending, or `None` once the
+/// connection is closed. Fails on lines longer than `MAX_LINE_LENGTH`.
+///
+/// Bytes read so far are kept in `buf`, so the read can be cancelled and resumed.
+async fn read_line<R>(reader: &mut R, buf: &mut Vec<u8>) -> io::Result<Option<String>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let limit = (MAX_LINE_LENGTH + 1).saturating_sub(buf.len()) as u64;
+    (&mut *reader).take(limit).read_until(b'\n', buf).await?;
+
+    if buf.len() > MAX_LINE_LENGTH {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Line too long"));
+    }
+    // Without a line ending, the connection was closed
+    if buf.is_empty() {
+        return Ok(None);
+    }
+
+    let line = String::from_utf8(std::mem::take(buf))
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(Some(line.trim_end_matches(&['\r', '\n'][..]).to_string()))
+}
+
+fn notify_message(job: &Job) -> Value {
+    json!({
+        "id": null,
+        "method": "mining.notify",
+        "params": [
+            job.id,
+            format!("{:x}", job.work.pre_hash),
+            format!("{:x}", job.work.difficulty),
+            true,
+            hex::encode(&job.work.params.personalization),
+        ],
+    })
+}
+
+fn response(id: Value, result: Value) -> Value {
+    json!({ "id": id, "result": result, "error": null })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "id": id, "result": null, "error": [code, message, null] })
+}
+
+async fn write_message(writer: &mut OwnedWriteHalf, message: &Value) -> io::Result<()> {
+    let mut bytes = serde_json::to_vec(message)?;
+    bytes.push(b'\n');
+    writer.write_all(&bytes).await
+}
@@ -0,0 +1,227 @@
+use super::*;
+use civicchain_pow::PowParams;
+use sp_consensus_pow::Seal as RawSeal;
+use sp_core::H256;
+
+// Work source offering the same job until `new_job` is called, and recording
+// submitted seals.
+#[derive(Clone, Default)]
+struct FakeWorker {
+    version: Arc<Mutex<u64>>,
+    submitted: Arc<Mutex<Vec<RawSeal>>>,
+}
+
+impl FakeWorker {
+    fn new_job(&self) {
+        *self.version.lock() += 1;
+    }
+}
+
+impl WorkSource for FakeWorker {
+    fn work(&self) -> Option<Work> {
+        Some(Work {
+            version: *self.version.lock() + 1,
+            pre_hash: H256::repeat_byte(7),
+            difficulty: U256::one(),
+            poh: H256::repeat_byte(8),
+            params: PowParams {
+                personalization: b"civic".to_vec(),
+            },
+        })
+    }
+
+    fn submit(&self, seal: RawSeal) -> BoxFuture<'static, bool> {
+        self.submitted.lock().push(seal);
+        async { true }.boxed()
+    }
+}
+
+// Minimal Stratum client speaking line-delimited JSON.
+struct FakeClient {
+    lines: tokio::io::Lines<BufReader<tokio::net::tcp::OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl FakeClient {
+    async fn connect(addr: SocketAddr) -> Self {
+        let (reader, writer) = TcpStream::connect(addr).await.unwrap().into_split();
+        Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+        }
+    }
+
+    async fn send(&mut self, message: Value) {
+        write_message(&mut self.writer, &message).await.unwrap();
+    }
+
+    // Read messages until one matches `predicate`.
+    async fn wait_for(&mut self, predicate: impl Fn(&Value) -> bool) -> Value {
+        loop {
+            let line = self.lines.next_line().await.unwrap().expect("connection closed");
+            let message: Value = serde_json::from_str(&line).unwrap();
+            if predicate(&message) {
+                return message;
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn fake_miner_gets_jobs_and_submits_solutions() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let worker = FakeWorker::default();
+
+    tokio::spawn(serve(listener, worker.clone(), U256::one(), |task| {
+        tokio::spawn(task);
+    }));
+
+    let mut client = FakeClient::connect(addr).await;
+
+    client
+        .send(json!({ "id": 1, "method": "mining.subscribe", "params": [] }))
+        .await;
+    let subscribed = client.wait_for(|message| message["id"] == 1).await;
+    assert_eq!(subscribed["result"][2], EXTRANONCE2_SIZE);
+
+    let job = client
+        .wait_for(|message| message["method"] == "mining.notify")
+        .await;
+    let job_id = job["params"][0].as_str().unwrap().to_string();
+    assert_eq!(job["params"][1], format!("{:x}", H256::repeat_byte(7)));
+    assert_eq!(job["params"][4], hex::encode(b"civic"));
+
+    // With difficulty one every hash is a valid share and a valid block.
+    client
+        .send(json!({ "id": 2, "method": "mining.submit", "params": ["phone", job_id, "00000001"] }))
+        .await;
+    let accepted = client.wait_for(|message| message["id"] == 2).await;
+    assert_eq!(accepted["result"], true);
+    assert_eq!(worker.submitted.lock().len(), 1);
+
+    // The seal carries the full fixed-width nonce and the job's PoH sequence.
+    let seal = CivicSeal::decode_raw(&worker.submitted.lock()[0]).unwrap();
+    assert_eq!(seal.nonce[EXTRANONCE1_SIZE..], [0, 0, 0, 1]);
+    assert_eq!(seal.poh, H256::repeat_byte(8));
+
+    // The same nonce cannot be credited twice.
+    client
+        .send(json!({ "id": 3, "method": "mining.submit", "params": ["phone", job_id, "00000001"] }))
+        .await;
+    let duplicate = client.wait_for(|message| message["id"] == 3).await;
+    assert_eq!(duplicate["error"][0], ERROR_DUPLICATE_SHARE);
+
+    // Unknown jobs are rejected.
+    client
+        .send(json!({ "id": 4, "method": "mining.submit", "params": ["phone", "ff", "00000002"] }))
+        .await;
+    let unknown = client.wait_for(|message| message["id"] == 4).await;
+    assert_eq!(unknown["error"][0], ERROR_JOB_NOT_FOUND);
+}
+
+// Serve `worker` on a local port.
+async fn start_server(worker: FakeWorker) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(serve(listener, worker, U256::one(), |task| {
+        tokio::spawn(task);
+    }));
+    addr
+}
+
+#[tokio::test]
+async fn every_connection_gets_its_own_extranonce() {
+    let addr = start_server(FakeWorker::default()).await;
+
+    let mut extranonces = HashSet::new();
+    for id in 0..4 {
+        let mut client = FakeClient::connect(addr).await;
+        client
+            .send(json!({ "id": id, "method": "mining.subscribe", "params": [] }))
+            .await;
+        let subscribed = client.wait_for(|message| message["id"] == id).await;
+        assert!(extranonces.insert(subscribed["result"][1].as_str().unwrap().to_string()));
+    }
+}
+
+#[tokio::test]
+async fn oversized_lines_close_the_connection() {
+    let addr = start_server(FakeWorker::default()).await;
+    let mut client = FakeClient::connect(addr).await;
+
+    client
+        .writer
+        .write_all(&vec![b'a'; MAX_LINE_LENGTH + 1])
+        .await
+        .unwrap();
+
+    // The server hangs up, or resets the connection as the line was not read in full.
+    assert!(!matches!(client.lines.next_line().await, Ok(Some(_))));
+}
+
+#[tokio::test]
+async fn share_submissions_are_rate_limited() {
+    let addr = start_server(FakeWorker::default()).await;
+    let mut client = FakeClient::connect(addr).await;
+    client
+        .send(json!({ "id": 0, "method": "mining.subscribe", "params": [] }))
+        .await;
+    client.wait_for(|message| message["id"] == 0).await;
+
+    // Invalid shares count too, so the limit is reached without hashing.
+    for id in 1..=MAX_SHARES_PER_WINDOW {
+        client
+            .send(json!({ "id": id, "method": "mining.submit", "params": ["phone", "ff", "zz"] }))
+            .await;
+        let rejected = client.wait_for(|message| message["id"] == id).await;
+        assert_ne!(rejected["error"][0], ERROR_TOO_MANY_SHARES);
+    }
+
+    let id = MAX_SHARES_PER_WINDOW + 1;
+    client
+        .send(json!({ "id": id, "method": "mining.submit", "params": ["phone", "ff", "zz"] }))
+        .await;
+    let limited = client.wait_for(|message| message["id"] == id).await;
+    assert_eq!(limited["error"][0], ERROR_TOO_MANY_SHARES);
+}
+
+#[tokio::test]
+async fn shares_stay_duplicates_while_their_job_is_open() {
+    let worker = FakeWorker::default();
+    let addr = start_server(worker.clone()).await;
+    let mut client = FakeClient::connect(addr).await;
+    client
+        .send(json!({ "id": 1, "method": "mining.subscribe", "params": [] }))
+        .await;
+    let job = client
+        .wait_for(|message| message["method"] == "mining.notify")
+        .await;
+    let job_id = job["params"][0].as_str().unwrap().to_string();
+
+    client
+        .send(json!({ "id": 2, "method": "mining.submit", "params": ["phone", job_id, "00000001"] }))
+        .await;
+    let accepted = client.wait_for(|message| message["id"] == 2).await;
+    assert_eq!(accepted["result"], true);
+
+    worker.new_job();
+    client
+        .wait_for(|message| message["method"] == "mining.notify" && message["params"][0] != job_id)
+        .await;
+
+    // The previous job still takes shares, but not the one already credited.
+    client
+        .send(json!({ "id": 3, "method": "mining.submit", "params": ["phone", job_id, "00000001"] }))
+        .await;
+    let duplicate = client.wait_for(|message| message["id"] == 3).await;
+    assert_eq!(duplicate["error"][0], ERROR_DUPLICATE_SHARE);
+
+    client
+        .send(json!({ "id": 4, "method": "mining.submit", "params": ["phone", job_id, "00000002"] }))
+        .await;
+    let accepted = client.wait_for(|message| message["id"] == 4).await;
+    assert_eq!(accepted["result"], true);
+    assert_eq!(worker.submitted.lock().len(), 2);
+}
@@ -0,0 +1,134 @@
+//! Helpers shared by the block import tests.
+
+use civicchain_pow::CivicSeal;
+use civicchain_runtime::opaque::{Block, Header};
+use parity_scale_codec::Encode;
+use sc_client_api::{backend::NewBlockState, in_mem, AuxStore};
+use sc_consensus::{
+    BlockCheckParams, BlockImport, BlockImportParams, ForkChoiceStrategy, ImportResult,
+};
+use sc_consensus_pow::{PowAux, POW_AUX_PREFIX};
+use sp_blockchain::HeaderBackend;
+use sp_consensus::{BlockOrigin, Error as ConsensusError};
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::{H256, U256};
+use sp_runtime::{traits::Header as HeaderT, Digest, DigestItem};
+
+/// Insert a genesis block into `blockchain` and return its hash.
+pub fn genesis(blockchain: &in_mem::Blockchain<Block>) -> H256 {
+    let genesis = Header::new(
+        0,
+        H256::zero(),
+        H256::zero(),
+        H256::zero(),
+        Digest::default(),
+    );
+    let hash = genesis.hash();
+    blockchain
+        .insert(hash, genesis, None, None, NewBlockState::Final)
+        .unwrap();
+    hash
+}
+
+/// Block sealed at `difficulty` on top of `parent`, which must be in `blockchain`.
+/// `salt` tells apart siblings.
+pub fn block(
+    blockchain: &in_mem::Blockchain<Block>,
+    parent: H256,
+    salt: u8,
+    difficulty: u64,
+) -> BlockImportParams<Block, ()> {
+    let number = blockchain.header(parent).unwrap().unwrap().number + 1;
+    let header = Header::new(
+        number,
+        H256::repeat_byte(salt),
+        H256::zero(),
+        parent,
+        Digest::default(),
+    );
+    let seal = CivicSeal::new(
+        difficulty.into(),
+        Default::default(),
+        H256::zero(),
+        H256::zero(),
+    );
+
+    let mut block = BlockImportParams::new(BlockOrigin::NetworkBroadcast, header);
+    block
+        .post_digests
+        .push(DigestItem::Seal(POW_ENGINE_ID, seal.encode()));
+    block
+}
+
+/// Innermost import standing in for the client behind `PowBlockImport`: stores the
+/// block in `blockchain`, as best if its fork choice says so or, without one, if it
+/// is the highest block, and writes its aux data along with the work it adds up to.
+pub struct TestImport {
+    pub blockchain: in_mem::Blockchain<Block>,
+}
+
+#[async_trait::async_trait]
+impl BlockImport<Block> for TestImport {
+    type Error = ConsensusError;
+    type Transaction = ();
+
+    async fn check_block(
+        &mut self,
+        _block: BlockCheckParams<Block>,
+    ) -> Result<ImportResult, Self::Error> {
+        Ok(ImportResult::imported(false))
+    }
+
+    async fn import_block(
+        &mut self,
+        block: BlockImportParams<Block, ()>,
+    ) -> Result<ImportResult, Self::Error> {
+        let import_error = |err: String| ConsensusError::ClientImport(err);
+        let hash = block.post_hash();
+        let header = block.post_header();
+
+        let best = match block.fork_choice {
+            Some(ForkChoiceStrategy::Custom(best)) => best,
+            _ => *header.number() > self.blockchain.info().best_number,
+        };
+        let state = if best {
+            NewBlockState::Best
+        } else {
+            NewBlockState::Normal
+        };
+
+        let mut aux = PowAux::<U256>::read::<_, Block>(&self.blockchain, header.parent_hash())
+            .map_err(|err| import_error(err.to_string()))?;
+        aux.difficulty = crate::ghost::seal_difficulty(&block.post_digests).unwrap_or_default();
+        aux.total_difficulty = aux.total_difficulty.saturating_add(aux.difficulty);
+        let aux_key: Vec<u8> = POW_AUX_PREFIX
+            .iter()
+            .chain(hash.as_ref())
+            .copied()
+            .collect();
+        let aux_value = aux.encode();
+
+        self.blockchain
+            .insert(hash, header, None, None, state)
+            .map_err(|err| import_error(err.to_string()))?;
+
+        let mut insert = vec![(&aux_key[..], &aux_value[..])];
+        insert.extend(
+            block
+                .auxiliary
+                .iter()
+                .filter_map(|(key, value)| Some((&key[..], &value.as_ref()?[..]))),
+        );
+        let delete: Vec<_> = block
+            .auxiliary
+            .iter()
+            .filter(|(_, value)| value.is_none())
+            .map(|(key, _)| &key[..])
+            .collect();
+        self.blockchain
+            .insert_aux(&insert, &delete)
+            .map_err(|err| import_error(err.to_string()))?;
+
+        Ok(ImportResult::imported(best))
+    }
+}
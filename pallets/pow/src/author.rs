@@ -0,0 +1,25 @@
+//! Block author carried in the PoW pre-runtime digest.
+//!
+//! The mining worker encodes the miner's `AccountId` into a `PreRuntime` digest
+//! under `POW_ENGINE_ID` before sealing, so the author is covered by the seal.
+
+use frame_support::traits::FindAuthor;
+use parity_scale_codec::{Decode, DecodeAll};
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_runtime::ConsensusEngineId;
+use sp_std::marker::PhantomData;
+
+/// `FindAuthor` implementation decoding the PoW pre-runtime digest.
+pub struct PowDigestAuthor<AccountId>(PhantomData<AccountId>);
+
+impl<AccountId: Decode> FindAuthor<AccountId> for PowDigestAuthor<AccountId> {
+    fn find_author<'a, I>(digests: I) -> Option<AccountId>
+    where
+        I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+    {
+        digests
+            .into_iter()
+            .filter(|(id, _)| *id == POW_ENGINE_ID)
+            .find_map(|(_, mut data)| AccountId::decode_all(&mut data).ok())
+    }
+}
@@ -0,0 +1,337 @@
+//! Benchmarks for the PoW pallet.
+
+use super::*;
+use crate::{compute_work, CivicSeal, PohDigest};
+use frame_benchmarking::v2::*;
+use frame_support::{
+    traits::{Currency, Hooks},
+    BoundedVec,
+};
+use frame_system::{pallet_prelude::*, RawOrigin};
+use parity_scale_codec::{Decode, Encode};
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::{H256, U256};
+use sp_runtime::{
+    traits::{Hash, Header as HeaderT, One, Zero},
+    Digest, DigestItem,
+};
+use sp_std::prelude::*;
+
+// Store `p` active proposals of `proposal_type`, with ids `0..p`, whose voting ends at block
+// `voting_ends_at`
+fn add_active_proposals<T: Config>(
+    p: u32,
+    proposal_type: ProposalType,
+    voting_ends_at: BlockNumberFor<T>,
+) {
+    let proposer: T::AccountId = account("proposer", 0, 0);
+    for id in 0..p {
+        <Proposals<T>>::insert(
+            id,
+            Proposal {
+                id,
+                proposer: proposer.clone(),
+                proposal_type: proposal_type.clone(),
+                description: Vec::new(),
+                proposed_value: id.into(),
+                voting_ends_at,
+                votes_for: 1u32.into(),
+                votes_against: 0u32.into(),
+                turnout: 1u32.into(),
+                deposit: 0u32.into(),
+                status: ProposalStatus::Active,
+            },
+        );
+    }
+    <ActiveProposals<T>>::put(BoundedVec::truncate_from((0..p).collect::<Vec<_>>()));
+    <NextProposalId<T>>::put(p);
+}
+
+// An account with enough free balance to stake on and open a proposal
+fn funded_account<T: Config>(name: &'static str) -> T::AccountId {
+    let who: T::AccountId = account(name, 0, 0);
+    let balance = T::MinProposalStake::get()
+        .saturating_add(T::ProposalDeposit::get())
+        .saturating_add(T::Currency::minimum_balance())
+        .saturating_mul(10u32.into());
+    T::Currency::make_free_balance_be(&who, balance);
+    who
+}
+
+// A chain of `d - 1` delegations on `BlockReward` from a verified expert, whose end has voted
+// on `p` active proposals, and an account with nothing delegated yet to delegate to the expert
+fn delegation_chain<T: Config>(d: u32, p: u32) -> (T::AccountId, T::AccountId) {
+    let caller = funded_account::<T>("caller");
+    let chain: Vec<T::AccountId> = (0..d).map(|index| account("expert", index, 0)).collect();
+    let stake = T::MinProposalStake::get();
+    assert!(
+        Pallet::<T>::verify_expert(RawOrigin::Root.into(), chain[0].clone(), Vec::new()).is_ok()
+    );
+    for pair in chain.windows(2) {
+        <Delegations<T>>::insert(
+            &pair[0],
+            ProposalType::BlockReward,
+            Delegation {
+                expert: pair[1].clone(),
+                conviction: Conviction::Locked1x,
+                balance: stake,
+            },
+        );
+    }
+
+    let voter = chain[chain.len() - 1].clone();
+    let voting_ends_at = T::MaxVotingPeriod::get().into();
+    add_active_proposals::<T>(p, ProposalType::BlockReward, voting_ends_at);
+    for id in 0..p {
+        <Votes<T>>::insert(
+            id,
+            &voter,
+            Vote {
+                voter: voter.clone(),
+                proposal_id: id,
+                in_favor: true,
+                stake,
+                conviction: Conviction::Locked1x,
+                delegated_votes: stake,
+                delegated_capital: stake,
+            },
+        );
+    }
+
+    (caller, chain[0].clone())
+}
+
+#[benchmarks]
+mod benchmarks {
+    use super::*;
+
+    // An uncle `MaxUncleDepth` blocks deep, checked against full uncle and block lists.
+    // It is sealed at difficulty 1, which any work meets, so building it costs one hash.
+    #[benchmark]
+    fn include_uncle() {
+        let caller: T::AccountId = whitelisted_caller();
+        let author: T::AccountId = account("author", 0, 0);
+
+        let depth = T::MaxUncleDepth::get();
+        let now: BlockNumberFor<T> = depth.saturating_add(2).into();
+        let number = now - depth.into();
+        let parent = T::Hashing::hash(b"parent");
+        frame_system::BlockHash::<T>::insert(number - One::one(), parent);
+        frame_system::Pallet::<T>::set_block_number(now);
+
+        <RecentBlocks<T>>::put(
+            (0..T::DifficultyWindow::get())
+                .map(|offset| BlockInfo {
+                    number: now.saturating_sub(offset.into()),
+                    hash: Default::default(),
+                    parent_hash: Default::default(),
+                    timestamp: 0,
+                    author: None,
+                    difficulty: U256::one(),
+                    total_difficulty: U256::zero(),
+                    poh_hash: H256::zero(),
+                })
+                .rev()
+                .collect::<Vec<_>>(),
+        );
+
+        // Every slot but one is taken in the current block, and the others hold uncles
+        // included in earlier blocks
+        let uncles = T::MaxUncles::get().saturating_mul(depth.saturating_add(1));
+        <Uncles<T>>::put(
+            (1..uncles)
+                .map(|index| UncleInfo {
+                    number,
+                    hash: T::Hashing::hash_of(&index),
+                    included_in: now.saturating_sub((index / T::MaxUncles::get()).into()),
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        let poh = PohDigest::new(H256::decode(&mut parent.as_ref()).unwrap_or_default());
+        let digest = Digest {
+            logs: vec![
+                DigestItem::PreRuntime(POW_ENGINE_ID, author.encode()),
+                poh.to_digest_item(),
+            ],
+        };
+        let mut uncle = HeaderFor::<T>::new(
+            number,
+            Default::default(),
+            Default::default(),
+            parent,
+            digest,
+        );
+        let nonce = [0u8; crate::NONCE_SIZE];
+        let work = compute_work(
+            &Pallet::<T>::pow_params_at(number),
+            uncle.hash().as_ref(),
+            &nonce,
+        );
+        let seal = CivicSeal::new(U256::one(), nonce, poh.hash, work);
+        uncle
+            .digest_mut()
+            .push(DigestItem::Seal(POW_ENGINE_ID, seal.encode()));
+        let uncle_hash = uncle.hash();
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller), uncle);
+
+        let included = Pallet::<T>::uncles();
+        assert!(included.iter().any(|uncle| uncle.hash == uncle_hash));
+        assert_eq!(included.len() as u32, uncles);
+    }
+
+    // A sequence of `t` ticks on top of the parent block, all of which are checked
+    #[benchmark]
+    fn set_poh(t: Linear<0, { T::MaxPohTicks::get().min(u32::MAX.into()) as u32 }>) {
+        let parent = frame_system::Pallet::<T>::parent_hash();
+        let parent = H256::decode(&mut parent.as_ref()).unwrap_or_default();
+        let poh = PohDigest::compute(parent, t.into());
+
+        #[extrinsic_call]
+        _(RawOrigin::None, poh);
+
+        assert_eq!(Pallet::<T>::last_poh_hash(), poh.hash);
+    }
+
+    // `p` approved protocol upgrades ending together, on a block that also halves the reward
+    #[benchmark]
+    fn on_initialize(p: Linear<0, { T::MaxActiveProposals::get() }>) {
+        let now: BlockNumberFor<T> = 2u32.into();
+        frame_system::Pallet::<T>::set_block_number(now);
+        <HalvingPeriod<T>>::put(BlockNumberFor::<T>::one());
+        add_active_proposals::<T>(p, ProposalType::ProtocolUpgrade, One::one());
+
+        #[block]
+        {
+            Pallet::<T>::on_initialize(now);
+        }
+
+        assert!(Pallet::<T>::active_proposals().is_empty());
+        assert_eq!(Pallet::<T>::last_halving_block(), now);
+    }
+
+    // The author is rewarded and difficulty is retargeted over a full window
+    #[benchmark]
+    fn on_finalize() {
+        let author: T::AccountId = account("author", 0, 0);
+        let now: BlockNumberFor<T> = T::DifficultyWindow::get().saturating_add(1).into();
+        frame_system::Pallet::<T>::set_block_number(now);
+        <Author<T>>::put(author.clone());
+
+        <RecentBlocks<T>>::put(
+            (1..=T::DifficultyWindow::get())
+                .map(|offset| BlockInfo {
+                    number: now.saturating_sub(offset.into()),
+                    hash: Default::default(),
+                    parent_hash: Default::default(),
+                    timestamp: 0,
+                    author: None,
+                    difficulty: U256::one(),
+                    total_difficulty: U256::zero(),
+                    poh_hash: H256::zero(),
+                })
+                .rev()
+                .collect::<Vec<_>>(),
+        );
+
+        #[block]
+        {
+            Pallet::<T>::on_finalize(now);
+        }
+
+        assert!(!T::Currency::free_balance(&author).is_zero());
+    }
+
+    // A proposal opened next to `p` others, by a proposer whose votes are delegated to
+    #[benchmark]
+    fn create_proposal(p: Linear<0, { T::MaxActiveProposals::get().saturating_sub(1) }>) {
+        let caller = funded_account::<T>("caller");
+        add_active_proposals::<T>(p, ProposalType::BlockReward, One::one());
+        let delegated = T::MinProposalStake::get();
+        <DelegatedVotes<T>>::insert(&caller, ProposalType::BlockReward, delegated);
+        <DelegatedCapital<T>>::insert(&caller, ProposalType::BlockReward, delegated);
+
+        #[extrinsic_call]
+        _(
+            RawOrigin::Signed(caller.clone()),
+            ProposalType::BlockReward,
+            vec![0u8; 32],
+            1,
+            One::one(),
+        );
+
+        assert_eq!(Pallet::<T>::active_proposals().len() as u32, p + 1);
+        assert_eq!(Pallet::<T>::open_proposals(&caller), 1);
+    }
+
+    // A vote on one of `p` active proposals, by a voter whose votes are delegated to
+    #[benchmark]
+    fn vote(p: Linear<1, { T::MaxActiveProposals::get() }>) {
+        let caller = funded_account::<T>("caller");
+        let voting_ends_at = T::MaxVotingPeriod::get().into();
+        add_active_proposals::<T>(p, ProposalType::BlockReward, voting_ends_at);
+        let stake = T::MinProposalStake::get();
+        <DelegatedVotes<T>>::insert(&caller, ProposalType::BlockReward, stake);
+        <DelegatedCapital<T>>::insert(&caller, ProposalType::BlockReward, stake);
+
+        #[extrinsic_call]
+        _(
+            RawOrigin::Signed(caller.clone()),
+            p - 1,
+            true,
+            stake,
+            Conviction::Locked6x,
+        );
+
+        assert!(Pallet::<T>::votes(p - 1, &caller).is_some());
+    }
+
+    // A delegation at the start of a chain of `d` delegations, whose end has voted on `p`
+    // active proposals
+    #[benchmark]
+    fn delegate(
+        d: Linear<1, { T::MaxDelegationDepth::get() }>,
+        p: Linear<0, { T::MaxActiveProposals::get() }>,
+    ) {
+        let (caller, expert) = delegation_chain::<T>(d, p);
+        let stake = T::MinProposalStake::get();
+
+        #[extrinsic_call]
+        _(
+            RawOrigin::Signed(caller.clone()),
+            ProposalType::BlockReward,
+            expert,
+            Conviction::Locked6x,
+            stake,
+        );
+
+        assert!(Pallet::<T>::delegations(&caller, ProposalType::BlockReward).is_some());
+    }
+
+    // The delegation benchmarked in `delegate`, withdrawn
+    #[benchmark]
+    fn undelegate(
+        d: Linear<1, { T::MaxDelegationDepth::get() }>,
+        p: Linear<0, { T::MaxActiveProposals::get() }>,
+    ) {
+        let (caller, expert) = delegation_chain::<T>(d, p);
+        let stake = T::MinProposalStake::get();
+        assert!(Pallet::<T>::delegate(
+            RawOrigin::Signed(caller.clone()).into(),
+            ProposalType::BlockReward,
+            expert,
+            Conviction::Locked6x,
+            stake,
+        )
+        .is_ok());
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()), ProposalType::BlockReward);
+
+        assert!(Pallet::<T>::delegations(&caller, ProposalType::BlockReward).is_none());
+    }
+
+    impl_benchmark_test_suite!(Pallet, crate::mock::new_test_ext(), crate::mock::Test);
+}
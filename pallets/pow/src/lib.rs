@@ -2,8 +2,9 @@
 //!
 //! This pallet implements the Proof of Work (PoW) consensus algorithm using Yespower-R16,
 //! a lightweight mining algorithm that allows mining on common CPUs, including mobile phones.
-//! It also implements GHOST (Greedy Heaviest Observed Subtree), Proof-of-History (PoH),
-//! ghost confirmations, and an on-chain system for halving decisions and upgrades.
+//! The node picks chain heads with GHOST (Greedy Heaviest Observed Subtree); the pallet
+//! keeps the chain's total difficulty. It also implements Proof-of-History (PoH),
+//! uncle rewards for stale blocks, and an on-chain system for halving decisions and upgrades.
 //!
 //! ## Overview
 //!
@@ -12,9 +13,10 @@
 //! * Block reward control (25 CVX initially)
 //! * Halving mechanism every 5 years, governed by on-chain voting
 //! * Maximum supply limit of 29 million coins
-//! * GHOST to improve security with low hashrate
+//! * Total difficulty tracking for the node's GHOST fork choice and light clients
 //! * Proof-of-History for ordering blocks before consensus
-//! * Ghost confirmations to reward orphan block validation
+//! * Uncle inclusion to reward miners of valid stale blocks
+//! * Work function parameters switched at scheduled heights, without a hard fork
 //! * On-chain governance system for halving decisions and upgrades
 //! * Penalties for malicious behavior
 //! * Light client support with FlyClient protocol
@@ -23,6 +25,23 @@
 
 pub use pallet::*;
 
+pub mod author;
+pub mod poh;
+pub mod runtime_api;
+pub mod weights;
+pub use civicchain_primitives::{mmr, yespower};
+pub use author::PowDigestAuthor;
+pub use mmr::{MmrCommitment, MMR_ENGINE_ID};
+pub use poh::{PohDigest, POH_ENGINE_ID};
+pub use runtime_api::CivicPowApi;
+pub use weights::WeightInfo;
+pub use yespower::{
+    compute_work, meets_difficulty, CivicSeal, Nonce, PowParams, PowSchedule, NONCE_SIZE,
+};
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
 #[cfg(test)]
 mod mock;
 
@@ -33,20 +52,29 @@ mod tests;
 pub mod pallet {
     use frame_support::{
         pallet_prelude::*,
-        traits::{Currency, ExistenceRequirement, Get, Imbalance, OnUnbalanced, ReservableCurrency},
+        traits::{
+            Currency, ExistenceRequirement, FindAuthor, Get, Imbalance, LockIdentifier,
+            LockableCurrency, OnTimestampSet, OnUnbalanced, ReservableCurrency, Time,
+            WithdrawReasons,
+        },
         weights::Weight,
     };
     use frame_system::pallet_prelude::*;
-    use sp_consensus_pow::{Seal as PowSeal, TotalPower};
+    use crate::{
+        mmr::{MerkleMountainRange, MmrNode},
+        poh, CivicSeal, PohDigest, PowParams, PowSchedule, WeightInfo,
+    };
+    use sp_consensus_pow::POW_ENGINE_ID;
     use sp_core::{H256, U256};
     use sp_runtime::{
-        traits::{AccountIdConversion, BlakeTwo256, Hash, SaturatedConversion, Zero},
-        Perbill,
+        traits::{
+            AccountIdConversion, AtLeast32BitUnsigned, BlakeTwo256, Convert, Hash,
+            Header as HeaderT, IntegerSquareRoot, One, SaturatedConversion, Zero,
+        },
+        DigestItem, Perbill,
     };
     use sp_std::prelude::*;
-    use merkle_light::merkle::MerkleTree;
     use sha2::{Digest, Sha256};
-    use yesha256::yespower_r16;
 
     // Currency type definition for the pallet
     type BalanceOf<T> =
@@ -55,6 +83,25 @@ pub mod pallet {
         <T as frame_system::Config>::AccountId,
     >>::NegativeImbalance;
 
+    // Block record kept in the difficulty window (the author is not always known)
+    type RecordedBlockOf<T> = BlockInfo<
+        BlockNumberFor<T>,
+        <T as frame_system::Config>::Hash,
+        Option<<T as frame_system::Config>::AccountId>,
+    >;
+
+    // Solve times longer than this many target spacings are capped in the LWMA
+    const MAX_SOLVE_TIME_FACTOR: u64 = 6;
+
+    // The author of a block including an uncle earns this fraction of the block reward
+    const UNCLE_INCLUSION_DIVISOR: u32 = 32;
+
+    // Work function personalization of protocol version `v` is this prefix followed by `v`
+    const PROTOCOL_UPGRADE_PREFIX: [u8; 19] = *b"civicchain-protocol";
+
+    // Balance lock backing governance stakes
+    const GOVERNANCE_LOCK_ID: LockIdentifier = *b"civicgov";
+
     // Structure to store block information
     #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
     pub struct BlockInfo<BlockNumber, Hash, AccountId> {
@@ -73,11 +120,16 @@ pub mod pallet {
     pub struct Proposal<BlockNumber, AccountId, Balance> {
         pub id: u32,
         pub proposer: AccountId,
+        pub proposal_type: ProposalType,
         pub description: Vec<u8>,
         pub proposed_value: u128,
         pub voting_ends_at: BlockNumber,
         pub votes_for: Balance,
         pub votes_against: Balance,
+        /// Capital behind the votes, without conviction, delegated capital included.
+        pub turnout: Balance,
+        /// Deposit reserved from the proposer until the proposal is finalized.
+        pub deposit: Balance,
         pub status: ProposalStatus,
     }
 
@@ -90,15 +142,68 @@ pub mod pallet {
         Executed,
     }
 
-    // Proposal type
+    // Proposal type, which sets how the proposed value is applied once approved
     #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
     pub enum ProposalType {
+        /// New block reward, in the smallest unit.
         BlockReward,
+        /// New number of blocks between halvings.
         HalvingPeriod,
+        /// New target spacing between blocks, in milliseconds.
         DifficultyAdjustment,
+        /// Version of the work function parameters to switch to after `MinPowParamsDelay`.
         ProtocolUpgrade,
     }
 
+    // Conviction of a vote. Each level multiplies the votes a stake counts for, and the
+    // number of `StakeLockPeriod`s it stays locked after the voting ends.
+    #[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Default, RuntimeDebug, TypeInfo)]
+    pub enum Conviction {
+        /// 0.1x votes, unlocked as soon as the voting ends.
+        #[default]
+        None,
+        /// 1x votes, locked for one period.
+        Locked1x,
+        /// 2x votes, locked for 2 periods.
+        Locked2x,
+        /// 3x votes, locked for 4 periods.
+        Locked3x,
+        /// 4x votes, locked for 8 periods.
+        Locked4x,
+        /// 5x votes, locked for 16 periods.
+        Locked5x,
+        /// 6x votes, locked for 32 periods.
+        Locked6x,
+    }
+
+    impl Conviction {
+        /// Votes counted for `capital`.
+        pub fn votes<B: AtLeast32BitUnsigned + Copy>(self, capital: B) -> B {
+            match self {
+                Conviction::None => capital / 10u32.into(),
+                Conviction::Locked1x => capital,
+                Conviction::Locked2x => capital.saturating_mul(2u32.into()),
+                Conviction::Locked3x => capital.saturating_mul(3u32.into()),
+                Conviction::Locked4x => capital.saturating_mul(4u32.into()),
+                Conviction::Locked5x => capital.saturating_mul(5u32.into()),
+                Conviction::Locked6x => capital.saturating_mul(6u32.into()),
+            }
+        }
+
+        /// Number of `StakeLockPeriod`s the stake stays locked after the voting ends.
+        pub fn lock_periods(self) -> u32 {
+            match self {
+                Conviction::None => 0,
+                Conviction::Locked1x => 1,
+                Conviction::Locked2x => 2,
+                Conviction::Locked3x => 4,
+                Conviction::Locked4x => 8,
+                Conviction::Locked5x => 16,
+                Conviction::Locked6x => 32,
+            }
+        }
+    }
+
     // Structure to store vote information
     #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
     pub struct Vote<AccountId, Balance> {
@@ -106,8 +211,17 @@ pub mod pallet {
         pub proposal_id: u32,
         pub in_favor: bool,
         pub stake: Balance,
-        pub delegated_to: Option<AccountId>,
-        pub weight: Perbill,
+        pub conviction: Conviction,
+        pub delegated_votes: Balance,
+        pub delegated_capital: Balance,
+    }
+
+    // Structure to store a standing delegation of votes on one proposal type
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+    pub struct Delegation<AccountId, Balance> {
+        pub expert: AccountId,
+        pub conviction: Conviction,
+        pub balance: Balance,
     }
 
     // Structure to store verified expert information
@@ -120,12 +234,12 @@ pub mod pallet {
         pub correct_votes: u32,
     }
 
-    // Structure to store orphan block information
+    // Structure to store an uncle included in the chain
     #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
-    pub struct OrphanBlock<BlockNumber, Hash, AccountId> {
-        pub info: BlockInfo<BlockNumber, Hash, AccountId>,
-        pub validators: Vec<AccountId>,
-        pub is_rewarded: bool,
+    pub struct UncleInfo<BlockNumber, Hash> {
+        pub number: BlockNumber,
+        pub hash: Hash,
+        pub included_in: BlockNumber,
     }
 
     // Pallet definition
@@ -139,11 +253,13 @@ pub mod pallet {
         /// The event type.
         type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
 
-        /// The currency in which rewards are paid.
-        type Currency: Currency<Self::AccountId> + ReservableCurrency<Self::AccountId>;
+        /// The currency in which rewards are paid, governance stakes are locked and proposal
+        /// deposits are reserved.
+        type Currency: LockableCurrency<Self::AccountId, Moment = BlockNumberFor<Self>>
+            + ReservableCurrency<Self::AccountId>;
 
-        /// Handler for mining rewards.
-        type RewardHandler: OnUnbalanced<NegativeImbalanceOf<Self>>;
+        /// Finds the author of the current block from its pre-runtime digests.
+        type FindAuthor: FindAuthor<Self::AccountId>;
 
         /// Base reward per block (25 CVX initially).
         #[pallet::constant]
@@ -160,6 +276,92 @@ pub mod pallet {
         /// Maximum coin supply (29 million).
         #[pallet::constant]
         type MaxSupply: Get<BalanceOf<Self>>;
+
+        /// Source of block timestamps, in milliseconds.
+        type Time: Time<Moment = u64>;
+
+        /// Number of blocks in the LWMA difficulty window.
+        #[pallet::constant]
+        type DifficultyWindow: Get<u32>;
+
+        /// Target spacing between blocks, in milliseconds.
+        #[pallet::constant]
+        type TargetBlockTime: Get<u64>;
+
+        /// Maximum factor by which difficulty may rise or fall in a single block.
+        #[pallet::constant]
+        type DifficultyClampFactor: Get<u32>;
+
+        /// Number of recent blocks whose median timestamp a new block must exceed.
+        /// Should not be larger than `DifficultyWindow + 1`, the number of blocks recorded.
+        #[pallet::constant]
+        type MedianTimeSpan: Get<u32>;
+
+        /// How many blocks below the current one an uncle may be. Must not be larger than
+        /// `DifficultyWindow` or `BlockHashCount`, which the uncle is checked against.
+        #[pallet::constant]
+        type MaxUncleDepth: Get<u32>;
+
+        /// Maximum number of uncles included per block.
+        #[pallet::constant]
+        type MaxUncles: Get<u32>;
+
+        /// Maximum number of Proof-of-History ticks between two blocks. Bounds the cost of
+        /// checking a block's sequence.
+        #[pallet::constant]
+        type MaxPohTicks: Get<u64>;
+
+        /// Minimum number of blocks between scheduling new work function parameters and
+        /// their activation, so miners have time to upgrade.
+        #[pallet::constant]
+        type MinPowParamsDelay: Get<u32>;
+
+        /// Stake the proposer locks behind a new governance proposal.
+        #[pallet::constant]
+        type MinProposalStake: Get<BalanceOf<Self>>;
+
+        /// Deposit reserved from the proposer for each proposal, returned once it is
+        /// finalized. Unlike stakes, deposits of different proposals add up.
+        #[pallet::constant]
+        type ProposalDeposit: Get<BalanceOf<Self>>;
+
+        /// Maximum number of proposals open for voting at once. Bounds the proposals looked
+        /// at in each block.
+        #[pallet::constant]
+        type MaxActiveProposals: Get<u32>;
+
+        /// Maximum number of proposals an account can have open for voting at once.
+        #[pallet::constant]
+        type MaxProposalsPerAccount: Get<u32>;
+
+        /// Maximum number of delegations in a chain. Bounds the delegations followed when
+        /// delegating or withdrawing a delegation.
+        #[pallet::constant]
+        type MaxDelegationDepth: Get<u32>;
+
+        /// Number of blocks stakes stay locked after the voting they back has ended.
+        #[pallet::constant]
+        type StakeLockPeriod: Get<u32>;
+
+        /// Maximum number of blocks a proposal can be voted on. Halvings are held back by
+        /// open `HalvingPeriod` proposals for at most this many blocks.
+        #[pallet::constant]
+        type MaxVotingPeriod: Get<u32>;
+
+        /// Fraction of the total issuance that has to back the votes on a proposal for it to
+        /// be decided. Counts the capital staked, without conviction.
+        ///
+        /// Measured against `Currency::total_issuance` rather than `TotalSupply`: genesis
+        /// balances are not in `TotalSupply` but can vote, while burned fees are out of the
+        /// issuance and can no longer vote.
+        #[pallet::constant]
+        type Quorum: Get<Perbill>;
+
+        /// Share of the votes a proposal of each type needs in favor to be approved.
+        type ApprovalThreshold: Convert<ProposalType, Perbill>;
+
+        /// Weight information for the extrinsics of this pallet.
+        type WeightInfo: WeightInfo;
     }
 
     // Events emitted by the pallet
@@ -174,7 +376,7 @@ pub mod pallet {
         /// [current_block, new_reward]
         HalvingOccurred(BlockNumberFor<T>, BalanceOf<T>),
 
-        /// The maximum supply was reached.
+        /// The maximum supply was reached. Emitted once, after which miners only earn fees.
         /// [current_block, total_supply]
         MaxSupplyReached(BlockNumberFor<T>, BalanceOf<T>),
 
@@ -202,17 +404,34 @@ pub mod pallet {
         /// [account, expertise]
         ExpertVerified(T::AccountId, Vec<u8>),
 
-        /// A reward was paid for orphan block validation.
-        /// [validator, amount, block_hash]
-        OrphanBlockRewardPaid(T::AccountId, BalanceOf<T>, H256),
+        /// An uncle was included and its miner rewarded.
+        /// [uncle_hash, uncle_author, amount]
+        UncleIncluded(T::Hash, T::AccountId, BalanceOf<T>),
 
         /// A penalty was applied for malicious behavior.
         /// [account, amount, reason]
         PenaltyApplied(T::AccountId, BalanceOf<T>, Vec<u8>),
 
-        /// A new block was added to the GHOST tree.
-        /// [block_hash, total_difficulty]
-        BlockAddedToGhost(H256, U256),
+        /// New work function parameters were scheduled.
+        /// [activation_block, params]
+        PowParamsScheduled(BlockNumberFor<T>, PowParams),
+
+        /// A stake was locked. Everything the account has locked is released at once.
+        /// [account, locked, release_block]
+        StakeLocked(T::AccountId, BalanceOf<T>, BlockNumberFor<T>),
+
+        /// A locked stake was released.
+        /// [account, amount]
+        StakeUnlocked(T::AccountId, BalanceOf<T>),
+
+        /// Votes on a proposal type were delegated to an expert.
+        /// [delegator, expert, proposal_type, votes]
+        Delegated(T::AccountId, T::AccountId, ProposalType, BalanceOf<T>),
+
+        /// A delegation was withdrawn.
+        /// [delegator, proposal_type]
+        Undelegated(T::AccountId, ProposalType),
+
     }
 
     // Errors that can occur in the pallet
@@ -236,20 +455,56 @@ pub mod pallet {
         AlreadyVoted,
         /// Not a verified expert.
         NotVerifiedExpert,
-        /// Orphan block not found.
-        OrphanBlockNotFound,
-        /// Orphan block already rewarded.
-        OrphanBlockAlreadyRewarded,
+        /// The uncle is not below the current block, or is part of the chain.
+        UncleNotStale,
+        /// The uncle is more than `MaxUncleDepth` blocks below the current block.
+        UncleTooOld,
+        /// The uncle's parent is not part of the chain.
+        UncleParentNotInChain,
+        /// The uncle was already included.
+        UncleAlreadyIncluded,
+        /// The current block already includes `MaxUncles` uncles.
+        TooManyUncles,
+        /// The uncle's seal is missing or its work does not meet the expected difficulty.
+        InvalidUncleSeal,
+        /// The uncle carries no author digest.
+        UncleAuthorNotFound,
         /// Proof-of-History verification failed.
         PohVerificationFailed,
         /// FlyClient verification failed.
         FlyClientVerificationFailed,
         /// GHOST tree verification failed.
         GhostVerificationFailed,
-        /// Stake already locked.
-        StakeAlreadyLocked,
         /// Lock period not finished.
         LockPeriodNotFinished,
+        /// Work function parameters must activate at least `MinPowParamsDelay` blocks ahead.
+        PowParamsActivationTooSoon,
+        /// Work function parameters are malformed.
+        InvalidPowParams,
+        /// The proposed value cannot be applied by the proposal type.
+        InvalidProposedValue,
+        /// No stake is locked.
+        NoStakeLocked,
+        /// Votes on this proposal type are already delegated.
+        AlreadyDelegating,
+        /// Votes on this proposal type are not delegated.
+        NotDelegating,
+        /// The expert delegates, directly or not, to the delegator.
+        DelegationCycle,
+        /// Votes on this proposal type are delegated, so the account cannot vote on it.
+        VotesDelegated,
+        /// The account voted on an active proposal of this type.
+        VotedOnActiveProposal,
+        /// The stake backs a delegation still in place.
+        StakeDelegated,
+        /// The voting period is longer than `MaxVotingPeriod`.
+        VotingPeriodTooLong,
+        /// `MaxActiveProposals` proposals are already open for voting.
+        TooManyActiveProposals,
+        /// The proposer already has `MaxProposalsPerAccount` proposals open for voting.
+        TooManyProposals,
+        /// The delegation would make a chain longer than `MaxDelegationDepth` delegations.
+        DelegationTooDeep,
     }
 
     // Storage for total supply issued
@@ -262,51 +517,82 @@ pub mod pallet {
     #[pallet::getter(fn current_block_reward)]
     pub type CurrentBlockReward<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
 
+    // Storage for the fees-only reward mode, entered once the maximum supply is reached
+    #[pallet::storage]
+    #[pallet::getter(fn fees_only)]
+    pub type FeesOnly<T: Config> = StorageValue<_, bool, ValueQuery>;
+
     // Storage for last halving block
     #[pallet::storage]
     #[pallet::getter(fn last_halving_block)]
     pub type LastHalvingBlock<T: Config> = StorageValue<_, BlockNumberFor<T>, ValueQuery>;
 
+    // Storage for the blocks between halvings, once set by governance
+    #[pallet::storage]
+    pub type HalvingPeriod<T: Config> = StorageValue<_, BlockNumberFor<T>, OptionQuery>;
+
     // Storage for current difficulty
     #[pallet::storage]
     #[pallet::getter(fn current_difficulty)]
     pub type CurrentDifficulty<T: Config> = StorageValue<_, U256, ValueQuery>;
 
+    // Storage for the target spacing between blocks in milliseconds, once set by governance
+    #[pallet::storage]
+    pub type BlockTimeTarget<T: Config> = StorageValue<_, u64, OptionQuery>;
+
+    // Storage for the author of the current block, left in state so it can be queried at any block
+    #[pallet::storage]
+    #[pallet::getter(fn author)]
+    pub type Author<T: Config> = StorageValue<_, T::AccountId, OptionQuery>;
+
+    // Storage for the most recent blocks, used for difficulty retargeting
+    #[pallet::storage]
+    #[pallet::getter(fn recent_blocks)]
+    pub type RecentBlocks<T: Config> = StorageValue<_, Vec<RecordedBlockOf<T>>, ValueQuery>;
+
     // Storage for last Proof-of-History hash
     #[pallet::storage]
     #[pallet::getter(fn last_poh_hash)]
     pub type LastPohHash<T: Config> = StorageValue<_, H256, ValueQuery>;
 
-    // Storage for Proof-of-History counter
+    // Storage for Proof-of-History counter, the total number of ticks since genesis
     #[pallet::storage]
     #[pallet::getter(fn poh_counter)]
     pub type PohCounter<T: Config> = StorageValue<_, u64, ValueQuery>;
 
-    // Storage for GHOST tree
-    #[pallet::storage]
-    #[pallet::getter(fn ghost_tree)]
-    pub type GhostTree<T: Config> = StorageMap<_, Blake2_128Concat, H256, BlockInfo<BlockNumberFor<T>, T::Hash, T::AccountId>, OptionQuery>;
-
-    // Storage for total difficulty of each block
+    // Whether the Proof-of-History sequence was set in the current block
     #[pallet::storage]
-    #[pallet::getter(fn block_total_difficulty)]
-    pub type BlockTotalDifficulty<T: Config> = StorageMap<_, Blake2_128Concat, H256, U256, ValueQuery>;
+    pub type DidUpdatePoh<T: Config> = StorageValue<_, bool, ValueQuery>;
 
-    // Storage for current best block (chain head)
+    // Storage for the cumulative difficulty of the chain, up to the last recorded block.
+    // The GHOST tree itself is kept by the node, outside of state.
     #[pallet::storage]
-    #[pallet::getter(fn best_block)]
-    pub type BestBlock<T: Config> = StorageValue<_, H256, ValueQuery>;
+    #[pallet::getter(fn total_difficulty)]
+    pub type TotalDifficulty<T: Config> = StorageValue<_, U256, ValueQuery>;
 
-    // Storage for orphan blocks
+    // Storage for uncles recent enough to be included again, used to prevent duplicates
     #[pallet::storage]
-    #[pallet::getter(fn orphan_blocks)]
-    pub type OrphanBlocks<T: Config> = StorageMap<_, Blake2_128Concat, H256, OrphanBlock<BlockNumberFor<T>, T::Hash, T::AccountId>, OptionQuery>;
+    #[pallet::getter(fn uncles)]
+    pub type Uncles<T: Config> =
+        StorageValue<_, Vec<UncleInfo<BlockNumberFor<T>, T::Hash>>, ValueQuery>;
 
     // Storage for governance proposals
     #[pallet::storage]
     #[pallet::getter(fn proposals)]
     pub type Proposals<T: Config> = StorageMap<_, Blake2_128Concat, u32, Proposal<BlockNumberFor<T>, T::AccountId, BalanceOf<T>>, OptionQuery>;
 
+    // Storage for the IDs of proposals still open for voting, finalized once their voting ends
+    #[pallet::storage]
+    #[pallet::getter(fn active_proposals)]
+    pub type ActiveProposals<T: Config> =
+        StorageValue<_, BoundedVec<u32, T::MaxActiveProposals>, ValueQuery>;
+
+    // Storage for the number of proposals each account has open for voting
+    #[pallet::storage]
+    #[pallet::getter(fn open_proposals)]
+    pub type OpenProposals<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
     // Storage for next proposal ID
     #[pallet::storage]
     #[pallet::getter(fn next_proposal_id)]
@@ -323,12 +609,61 @@ pub mod pallet {
         OptionQuery,
     >;
 
+    // Storage for standing delegations, by delegator and proposal type
+    #[pallet::storage]
+    #[pallet::getter(fn delegations)]
+    pub type Delegations<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat, T::AccountId, // Delegator
+        Blake2_128Concat, ProposalType, // Proposal type
+        Delegation<T::AccountId, BalanceOf<T>>,
+        OptionQuery,
+    >;
+
+    // Storage for the votes delegated to each account on a proposal type, directly or through
+    // other delegates. They count on the side of every vote the account casts on that type.
+    #[pallet::storage]
+    #[pallet::getter(fn delegated_votes)]
+    pub type DelegatedVotes<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat, T::AccountId, // Delegate
+        Blake2_128Concat, ProposalType, // Proposal type
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    // Storage for the capital behind `DelegatedVotes`, without conviction, which counts in
+    // the turnout of every vote the account casts on that type
+    #[pallet::storage]
+    #[pallet::getter(fn delegated_capital)]
+    pub type DelegatedCapital<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat, T::AccountId, // Delegate
+        Blake2_128Concat, ProposalType, // Proposal type
+        BalanceOf<T>,
+        ValueQuery,
+    >;
+
+    // Storage for the number of delegations in the longest chain ending at each account on a
+    // proposal type. Withdrawn delegations are only accounted for once nothing is delegated
+    // to the account anymore, so this is an upper bound.
+    #[pallet::storage]
+    #[pallet::getter(fn delegation_depth)]
+    pub type DelegationDepth<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat, T::AccountId, // Delegate
+        Blake2_128Concat, ProposalType, // Proposal type
+        u32,
+        ValueQuery,
+    >;
+
     // Storage for verified experts
     #[pallet::storage]
     #[pallet::getter(fn verified_experts)]
     pub type VerifiedExperts<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, VerifiedExpert<T::AccountId>, OptionQuery>;
 
-    // Storage for locked stakes
+    // Storage for the governance lock of each account. Stakes lock the same funds, so the
+    // lock covers the largest of them, and is released at the latest release block.
     #[pallet::storage]
     #[pallet::getter(fn locked_stakes)]
     pub type LockedStakes<T: Config> = StorageMap<
@@ -338,10 +673,16 @@ pub mod pallet {
         OptionQuery,
     >;
 
-    // Storage for Merkle tree root for FlyClient
+    // Storage for the peaks of the FlyClient MMR over every block before the current one
+    #[pallet::storage]
+    #[pallet::getter(fn mmr)]
+    pub type Mmr<T: Config> = StorageValue<_, MerkleMountainRange, ValueQuery>;
+
+    // Storage for the work function parameters by activation height. Entries are never
+    // removed, so the blocks before each switch can still be verified.
     #[pallet::storage]
-    #[pallet::getter(fn merkle_root)]
-    pub type MerkleRoot<T: Config> = StorageValue<_, H256, ValueQuery>;
+    #[pallet::getter(fn pow_schedule)]
+    pub type PowParamsSchedule<T: Config> = StorageValue<_, PowSchedule, ValueQuery>;
 
     // Pallet genesis
     #[pallet::genesis_config]
@@ -375,8 +716,6 @@ pub mod pallet {
             <PohCounter<T>>::put(0);
             // Initialize next proposal ID
             <NextProposalId<T>>::put(1);
-            // Initialize Merkle tree root
-            <MerkleRoot<T>>::put(H256::zero());
         }
     }
 
@@ -384,120 +723,76 @@ pub mod pallet {
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
         fn on_initialize(block_number: BlockNumberFor<T>) -> Weight {
-            // Update PoH hash
-            Self::update_poh_hash();
+            // The previous block's hash is only known once its child is being built
+            let parent_hash = <frame_system::Pallet<T>>::parent_hash();
+            <RecentBlocks<T>>::mutate(|blocks| {
+                if let Some(last) = blocks.last_mut() {
+                    last.hash = parent_hash;
+                }
+            });
 
-            // Check if there are governance proposals to finalize
-            Self::finalize_proposals(block_number);
+            // Record who mined this block
+            <Author<T>>::set(Self::find_author());
 
-            // Check if it's time for a halving (if there's no active proposal to change the period)
-            if !Self::has_active_halving_proposal() {
-                let blocks_per_halving = T::BlocksPerYear::get()
-                    .saturating_mul(T::HalvingYears::get())
-                    .saturated_into::<u32>();
-                let blocks_per_halving = blocks_per_halving.saturated_into::<BlockNumberFor<T>>();
-
-                let last_halving = Self::last_halving_block();
-                if block_number.saturating_sub(last_halving) >= blocks_per_halving
-                    && !Self::current_block_reward().is_zero()
-                {
-                    // Perform halving
-                    let current_reward = Self::current_block_reward();
-                    let new_reward = current_reward.saturating_div(2u32.saturated_into());
-                    <CurrentBlockReward<T>>::put(new_reward);
-                    <LastHalvingBlock<T>>::put(block_number);
-
-                    // Emit halving event
-                    Self::deposit_event(Event::HalvingOccurred(block_number, new_reward));
-                }
-            }
+            // Uncles deeper than `MaxUncleDepth` can no longer be included, so forget them
+            let max_uncle_depth = T::MaxUncleDepth::get().saturated_into::<BlockNumberFor<T>>();
+            <Uncles<T>>::mutate(|uncles| {
+                uncles.retain(|uncle| block_number.saturating_sub(uncle.number) <= max_uncle_depth)
+            });
 
-            // Adjust difficulty every 2016 blocks (approximately 2 weeks with 200-second blocks)
-            if block_number % 2016u32.saturated_into::<BlockNumberFor<T>>() == Zero::zero() && block_number > Zero::zero() {
-                Self::adjust_difficulty(block_number);
+            // Check if there are governance proposals to finalize
+            let proposals = Self::finalize_proposals(block_number);
+
+            // Check if it's time for a halving. An active proposal to change the period holds
+            // it back, for at most `MaxVotingPeriod` blocks so that new proposals opened one
+            // after the other cannot hold it back forever.
+            let blocks_per_halving = Self::halving_period();
+            let since_halving = block_number.saturating_sub(Self::last_halving_block());
+            let held_back = Self::has_active_halving_proposal()
+                && since_halving
+                    < blocks_per_halving.saturating_add(T::MaxVotingPeriod::get().saturated_into());
+            if !held_back
+                && since_halving >= blocks_per_halving
+                && !Self::current_block_reward().is_zero()
+            {
+                // Perform halving
+                let current_reward = Self::current_block_reward();
+                let new_reward = current_reward.saturating_div(2u32.saturated_into());
+                <CurrentBlockReward<T>>::put(new_reward);
+                <LastHalvingBlock<T>>::put(block_number);
+
+                // Emit halving event
+                Self::deposit_event(Event::HalvingOccurred(block_number, new_reward));
             }
 
-            // Update Merkle tree for FlyClient every 1000 blocks
-            if block_number % 1000u32.saturated_into::<BlockNumberFor<T>>() == Zero::zero() && block_number > Zero::zero() {
-                Self::update_merkle_tree();
-            }
+            // Commit to the parent block in the FlyClient MMR
+            Self::update_mmr();
+
+            // `on_finalize` cannot return its weight, so it is accounted for here
+            T::WeightInfo::on_initialize(proposals).saturating_add(T::WeightInfo::on_finalize())
+        }
 
-            Weight::zero()
+        fn on_finalize(block_number: BlockNumberFor<T>) {
+            <DidUpdatePoh<T>>::kill();
+
+            // Mint the block reward to whoever sealed this block
+            Self::reward_author(block_number);
+
+            // Record this block and retarget difficulty for the next one
+            Self::record_block(block_number);
+            Self::adjust_difficulty();
         }
     }
 
     // External calls for the pallet
     #[pallet::call]
     impl<T: Config> Pallet<T> {
-        /// Submit a proof of work to receive the mining reward.
-        #[pallet::call_index(0)]
-        #[pallet::weight(10_000)]
-        pub fn submit_pow_solution(
-            origin: OriginFor<T>,
-            nonce: Vec<u8>,
-            solution: H256,
-            difficulty: U256,
-            poh_hash: H256,
-        ) -> DispatchResult {
-            let miner = ensure_signed(origin)?;
-
-            // Verify if the difficulty is valid
-            ensure!(
-                difficulty >= Self::current_difficulty(),
-                Error::<T>::DifficultyTooLow
-            );
+        // Call index 0 belonged to `submit_pow_solution`. Rewards are now minted to the
+        // block author in `on_finalize`, and seals are checked by the node on import.
 
-            // Verify the Proof-of-History hash
-            ensure!(
-                Self::verify_poh(poh_hash),
-                Error::<T>::PohVerificationFailed
-            );
-
-            // Verify the PoW solution
-            let pre_hash = <frame_system::Pallet<T>>::parent_hash();
-            let pre_hash_bytes = pre_hash.as_bytes();
-            
-            let seal = PowSeal {
-                difficulty,
-                work: solution,
-                nonce,
-            };
-
-            ensure!(
-                Self::verify_pow(pre_hash_bytes, &seal, poh_hash),
-                Error::<T>::PowVerificationFailed
-            );
-
-            // Calculate the reward
-            let reward = Self::calculate_reward()?;
-
-            // Pay the reward to the miner
-            if !reward.is_zero() {
-                let imbalance = T::Currency::issue(reward);
-                T::RewardHandler::on_unbalanced(imbalance);
-                T::Currency::deposit_creating(&miner, reward);
-
-                // Update total supply
-                let new_supply = Self::total_supply().saturating_add(reward);
-                <TotalSupply<T>>::put(new_supply);
-
-                // Emit reward event
-                let current_block = <frame_system::Pallet<T>>::block_number();
-                Self::deposit_event(Event::RewardPaid(miner, reward, current_block));
-            }
-
-            // Update GHOST tree
-            let current_block_hash = <frame_system::Pallet<T>>::block_hash(
-                <frame_system::Pallet<T>>::block_number()
-            );
-            Self::update_ghost_tree(current_block_hash, pre_hash, difficulty, miner.clone(), poh_hash);
-
-            Ok(())
-        }
-
-        /// Create a governance proposal.
+        /// Create a governance proposal. `ProposalDeposit` is reserved until it is finalized.
         #[pallet::call_index(1)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(T::WeightInfo::create_proposal(T::MaxActiveProposals::get()))]
         pub fn create_proposal(
             origin: OriginFor<T>,
             proposal_type: ProposalType,
@@ -506,53 +801,93 @@ pub mod pallet {
             voting_period: BlockNumberFor<T>,
         ) -> DispatchResult {
             let proposer = ensure_signed(origin)?;
+            ensure!(
+                Self::is_valid_proposed_value(&proposal_type, proposed_value),
+                Error::<T>::InvalidProposedValue
+            );
+            ensure!(
+                voting_period <= T::MaxVotingPeriod::get().saturated_into(),
+                Error::<T>::VotingPeriodTooLong
+            );
+            ensure!(
+                !<Delegations<T>>::contains_key(&proposer, &proposal_type),
+                Error::<T>::VotesDelegated
+            );
+            ensure!(
+                Self::open_proposals(&proposer) < T::MaxProposalsPerAccount::get(),
+                Error::<T>::TooManyProposals
+            );
 
-            // Verify if the proposer has enough stake (1000 coins)
-            let min_stake = 1000 * 10_u128.pow(18);
-            let min_stake_balance: BalanceOf<T> = min_stake.saturated_into();
+            // Reserve the deposit before locking the stake, which may cover the same funds
+            let deposit = T::ProposalDeposit::get();
+            T::Currency::reserve(&proposer, deposit)?;
+
+            // Verify if the proposer has enough stake
+            let min_stake_balance = T::MinProposalStake::get();
             ensure!(
                 T::Currency::free_balance(&proposer) >= min_stake_balance,
                 Error::<T>::InsufficientStakeForVoting
             );
 
-            // Lock the proposer's stake
-            T::Currency::reserve(&proposer, min_stake_balance)?;
-
             // Calculate the voting end block
             let current_block = <frame_system::Pallet<T>>::block_number();
             let voting_ends_at = current_block.saturating_add(voting_period);
 
+            // Lock the proposer's stake
+            Self::lock_stake(
+                &proposer,
+                min_stake_balance,
+                voting_ends_at,
+                Conviction::Locked1x,
+            );
+
             // Create the proposal
             let proposal_id = Self::next_proposal_id();
             let proposal = Proposal {
                 id: proposal_id,
                 proposer: proposer.clone(),
+                proposal_type: proposal_type.clone(),
                 description,
                 proposed_value,
                 voting_ends_at,
                 votes_for: BalanceOf::<T>::zero(),
                 votes_against: BalanceOf::<T>::zero(),
+                turnout: BalanceOf::<T>::zero(),
+                deposit,
                 status: ProposalStatus::Active,
             };
 
             // Store the proposal
+            <ActiveProposals<T>>::try_mutate(|active| active.try_push(proposal_id))
+                .map_err(|_| Error::<T>::TooManyActiveProposals)?;
+            <OpenProposals<T>>::mutate(&proposer, |open| *open = open.saturating_add(1));
             <Proposals<T>>::insert(proposal_id, proposal);
             <NextProposalId<T>>::put(proposal_id.saturating_add(1));
 
             // Register the proposer's vote (in favor)
+            let delegated_votes = Self::delegated_votes(&proposer, &proposal_type);
+            let delegated_capital = Self::delegated_capital(&proposer, &proposal_type);
             let vote = Vote {
                 voter: proposer.clone(),
                 proposal_id,
                 in_favor: true,
                 stake: min_stake_balance,
-                delegated_to: None,
-                weight: Perbill::from_percent(100),
+                conviction: Conviction::Locked1x,
+                delegated_votes,
+                delegated_capital,
             };
             <Votes<T>>::insert(proposal_id, proposer.clone(), vote);
 
             // Update the proposal's votes
             let mut proposal = Self::proposals(proposal_id).ok_or(Error::<T>::ProposalNotFound)?;
-            proposal.votes_for = proposal.votes_for.saturating_add(min_stake_balance);
+            proposal.votes_for = proposal
+                .votes_for
+                .saturating_add(min_stake_balance)
+                .saturating_add(delegated_votes);
+            proposal.turnout = proposal
+                .turnout
+                .saturating_add(min_stake_balance)
+                .saturating_add(delegated_capital);
             <Proposals<T>>::insert(proposal_id, proposal);
 
             // Emit proposal creation event
@@ -563,13 +898,13 @@ pub mod pallet {
 
         /// Vote on a governance proposal.
         #[pallet::call_index(2)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(T::WeightInfo::vote(T::MaxActiveProposals::get()))]
         pub fn vote(
             origin: OriginFor<T>,
             proposal_id: u32,
             in_favor: bool,
             stake: BalanceOf<T>,
-            delegated_to: Option<T::AccountId>,
+            conviction: Conviction,
         ) -> DispatchResult {
             let voter = ensure_signed(origin)?;
 
@@ -599,43 +934,42 @@ pub mod pallet {
                 Error::<T>::AlreadyVoted
             );
 
-            // Verify if the delegate is a verified expert
-            let weight = if let Some(ref expert) = delegated_to {
-                ensure!(
-                    <VerifiedExperts<T>>::contains_key(expert),
-                    Error::<T>::NotVerifiedExpert
-                );
-                
-                // Get the expert's accuracy to calculate vote weight
-                let expert_info = Self::verified_experts(expert).unwrap();
-                expert_info.accuracy
-            } else {
-                Perbill::from_percent(100)
-            };
+            // Votes on a delegated proposal type are cast by the expert
+            ensure!(
+                !<Delegations<T>>::contains_key(&voter, &proposal.proposal_type),
+                Error::<T>::VotesDelegated
+            );
 
             // Lock the voter's stake
-            T::Currency::reserve(&voter, stake)?;
+            Self::lock_stake(&voter, stake, proposal.voting_ends_at, conviction);
 
-            // Register the vote
+            // Register the vote, along with the votes delegated to the voter
+            let delegated_votes = Self::delegated_votes(&voter, &proposal.proposal_type);
+            let delegated_capital = Self::delegated_capital(&voter, &proposal.proposal_type);
             let vote = Vote {
                 voter: voter.clone(),
                 proposal_id,
                 in_favor,
                 stake,
-                delegated_to: delegated_to.clone(),
-                weight,
+                conviction,
+                delegated_votes,
+                delegated_capital,
             };
             <Votes<T>>::insert(proposal_id, voter.clone(), vote);
 
             // Update the proposal's votes
             let mut proposal = Self::proposals(proposal_id).ok_or(Error::<T>::ProposalNotFound)?;
-            let weighted_stake = weight.mul_floor(stake);
+            let weighted_stake = conviction.votes(stake).saturating_add(delegated_votes);
             
             if in_favor {
                 proposal.votes_for = proposal.votes_for.saturating_add(weighted_stake);
             } else {
                 proposal.votes_against = proposal.votes_against.saturating_add(weighted_stake);
             }
+            proposal.turnout = proposal
+                .turnout
+                .saturating_add(stake)
+                .saturating_add(delegated_capital);
             
             <Proposals<T>>::insert(proposal_id, proposal);
 
@@ -673,62 +1007,8 @@ pub mod pallet {
             Ok(())
         }
 
-        /// Validate an orphan block.
-        #[pallet::call_index(4)]
-        #[pallet::weight(10_000)]
-        pub fn validate_orphan_block(
-            origin: OriginFor<T>,
-            block_hash: H256,
-        ) -> DispatchResult {
-            let validator = ensure_signed(origin)?;
-
-            // Verify if the orphan block exists
-            let mut orphan_block = Self::orphan_blocks(block_hash).ok_or(Error::<T>::OrphanBlockNotFound)?;
-            
-            // Verify if the orphan block has already been rewarded
-            ensure!(
-                !orphan_block.is_rewarded,
-                Error::<T>::OrphanBlockAlreadyRewarded
-            );
-
-            // Add the validator to the orphan block's validators list
-            if !orphan_block.validators.contains(&validator) {
-                orphan_block.validators.push(validator.clone());
-                <OrphanBlocks<T>>::insert(block_hash, orphan_block.clone());
-            }
+        // Call index 4 belonged to `validate_orphan_block`, replaced by `include_uncle`.
 
-            // If there are at least 3 validators, pay the reward
-            if orphan_block.validators.len() >= 3 {
-                // Calculate the reward (20% of the block reward)
-                let block_reward = Self::current_block_reward();
-                let ghost_reward = block_reward.saturating_mul(20u32.saturated_into()) / 100u32.saturated_into();
-                
-                // Mark the orphan block as rewarded
-                orphan_block.is_rewarded = true;
-                <OrphanBlocks<T>>::insert(block_hash, orphan_block.clone());
-                
-                // Pay the reward to each validator
-                for validator in orphan_block.validators.iter() {
-                    let validator_reward = ghost_reward.saturating_div(orphan_block.validators.len().saturated_into());
-                    
-                    if !validator_reward.is_zero() {
-                        let imbalance = T::Currency::issue(validator_reward);
-                        T::RewardHandler::on_unbalanced(imbalance);
-                        T::Currency::deposit_creating(validator, validator_reward);
-                        
-                        // Update total supply
-                        let new_supply = Self::total_supply().saturating_add(validator_reward);
-                        <TotalSupply<T>>::put(new_supply);
-                        
-                        // Emit orphan block reward event
-                        Self::deposit_event(Event::OrphanBlockRewardPaid(validator.clone(), validator_reward, block_hash));
-                    }
-                }
-            }
-            
-            Ok(())
-        }
-        
         /// Apply a penalty for malicious behavior.
         #[pallet::call_index(5)]
         #[pallet::weight(10_000)]
@@ -748,19 +1028,873 @@ pub mod pallet {
             
             Ok(())
         }
+
+        /// Include the header of a stale block as an uncle of the current block.
+        ///
+        /// The uncle must be sealed with Yespower work at the difficulty of its height and
+        /// branch off the chain at most `MaxUncleDepth` blocks back. Its miner earns a share
+        /// of the block reward that shrinks with depth, and the author of the current block
+        /// earns 1/32 of it, so anyone may submit uncles. The call is weighted for the
+        /// Yespower hash it runs, which is paid for even if the uncle is rejected.
+        #[pallet::call_index(6)]
+        #[pallet::weight(T::WeightInfo::include_uncle())]
+        pub fn include_uncle(origin: OriginFor<T>, uncle: HeaderFor<T>) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let current_block = <frame_system::Pallet<T>>::block_number();
+            let (info, uncle_author) = Self::verify_uncle(current_block, uncle)?;
+            let depth: u32 = current_block.saturating_sub(info.number).saturated_into();
+
+            // Reward the uncle miner with (D + 2 - depth) / (D + 2) of the block reward,
+            // D being `MaxUncleDepth`
+            let block_reward = Self::current_block_reward();
+            let shares = T::MaxUncleDepth::get().saturating_add(2);
+            let uncle_reward = block_reward
+                .saturating_mul(shares.saturating_sub(depth).saturated_into())
+                / shares.saturated_into();
+            let uncle_reward = Self::issue_reward(&uncle_author, uncle_reward);
+
+            // Reward the miner of the current block for including it
+            if let Some(author) = Self::author() {
+                let inclusion_reward =
+                    Self::issue_reward(&author, block_reward / UNCLE_INCLUSION_DIVISOR.saturated_into());
+                if !inclusion_reward.is_zero() {
+                    Self::deposit_event(Event::RewardPaid(author, inclusion_reward, current_block));
+                }
+            }
+
+            let uncle_hash = info.hash;
+            <Uncles<T>>::append(info);
+
+            // Emit uncle included event
+            Self::deposit_event(Event::UncleIncluded(uncle_hash, uncle_author, uncle_reward));
+
+            Ok(())
+        }
+
+        /// Set the Proof-of-History sequence of the current block.
+        ///
+        /// Provided as an inherent by the block producer. The sequence must start at the
+        /// parent hash; it is deposited as a header digest so it can be checked from the
+        /// header alone. Checking it costs one hash per tick, up to `MaxPohTicks`.
+        #[pallet::call_index(7)]
+        #[pallet::weight((
+            T::WeightInfo::set_poh(poh.ticks.min(T::MaxPohTicks::get()).saturated_into()),
+            DispatchClass::Mandatory,
+        ))]
+        pub fn set_poh(origin: OriginFor<T>, poh: PohDigest) -> DispatchResult {
+            ensure_none(origin)?;
+            assert!(
+                !<DidUpdatePoh<T>>::exists(),
+                "Proof-of-History must be set only once in the block"
+            );
+
+            ensure!(
+                poh.verify_with(Self::parent_hash(), T::MaxPohTicks::get(), poh::native_tick),
+                Error::<T>::PohVerificationFailed
+            );
+
+            <LastPohHash<T>>::put(poh.hash);
+            <PohCounter<T>>::mutate(|counter| *counter = counter.saturating_add(poh.ticks));
+            <DidUpdatePoh<T>>::put(true);
+            <frame_system::Pallet<T>>::deposit_log(poh.to_digest_item());
+
+            Ok(())
+        }
+
+        /// Schedule new work function parameters, used from block `activation` on.
+        ///
+        /// Blocks are verified by height, so the switch needs no hard fork. Scheduling
+        /// again at the same height replaces the earlier parameters.
+        #[pallet::call_index(8)]
+        #[pallet::weight(10_000)]
+        pub fn schedule_pow_params(
+            origin: OriginFor<T>,
+            activation: BlockNumberFor<T>,
+            params: PowParams,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            Self::do_schedule_pow_params(activation, params)
+        }
+
+        /// Release the caller's locked stake, once `StakeLockPeriod` blocks have passed since
+        /// the end of the last voting it backs and it backs no delegation.
+        #[pallet::call_index(9)]
+        #[pallet::weight(10_000)]
+        pub fn unlock_stake(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (amount, release) = Self::locked_stakes(&who).ok_or(Error::<T>::NoStakeLocked)?;
+            let current_block = <frame_system::Pallet<T>>::block_number();
+            ensure!(current_block >= release, Error::<T>::LockPeriodNotFinished);
+            ensure!(
+                <Delegations<T>>::iter_prefix_values(&who).next().is_none(),
+                Error::<T>::StakeDelegated
+            );
+
+            T::Currency::remove_lock(GOVERNANCE_LOCK_ID, &who);
+            <LockedStakes<T>>::remove(&who);
+
+            Self::deposit_event(Event::StakeUnlocked(who, amount));
+
+            Ok(())
+        }
+
+        /// Delegate votes on proposals of `proposal_type` to a verified expert. `balance` is
+        /// locked with `conviction` and counts on the side of every vote the expert casts on
+        /// that type, including votes already cast on active proposals, until `undelegate`.
+        #[pallet::call_index(10)]
+        #[pallet::weight(T::WeightInfo::delegate(
+            T::MaxDelegationDepth::get(),
+            T::MaxActiveProposals::get(),
+        ))]
+        pub fn delegate(
+            origin: OriginFor<T>,
+            proposal_type: ProposalType,
+            expert: T::AccountId,
+            conviction: Conviction,
+            balance: BalanceOf<T>,
+        ) -> DispatchResult {
+            let delegator = ensure_signed(origin)?;
+
+            ensure!(
+                <VerifiedExperts<T>>::contains_key(&expert),
+                Error::<T>::NotVerifiedExpert
+            );
+            ensure!(
+                !<Delegations<T>>::contains_key(&delegator, &proposal_type),
+                Error::<T>::AlreadyDelegating
+            );
+            // Nothing delegated to an account means no delegation ends at it
+            ensure!(
+                !balance.is_zero() && T::Currency::free_balance(&delegator) >= balance,
+                Error::<T>::InsufficientStakeForVoting
+            );
+
+            // Votes already cast would count next to the delegated ones
+            ensure!(
+                !Self::has_active_vote(&delegator, &proposal_type),
+                Error::<T>::VotedOnActiveProposal
+            );
+
+            // Follow the expert's own delegations, which must not lead back to the delegator,
+            // counting those that end at the delegator
+            let mut depth = Self::delegation_depth(&delegator, &proposal_type).saturating_add(1);
+            let mut delegate = expert.clone();
+            loop {
+                ensure!(delegate != delegator, Error::<T>::DelegationCycle);
+                ensure!(
+                    depth <= T::MaxDelegationDepth::get(),
+                    Error::<T>::DelegationTooDeep
+                );
+                match Self::delegations(&delegate, &proposal_type) {
+                    Some(delegation) => {
+                        delegate = delegation.expert;
+                        depth = depth.saturating_add(1);
+                    }
+                    None => break,
+                }
+            }
+
+            let current_block = <frame_system::Pallet<T>>::block_number();
+            Self::lock_stake(&delegator, balance, current_block, conviction);
+            <Delegations<T>>::insert(
+                &delegator,
+                &proposal_type,
+                Delegation {
+                    expert: expert.clone(),
+                    conviction,
+                    balance,
+                },
+            );
+
+            // Votes delegated to the delegator move along with its own
+            let votes = conviction
+                .votes(balance)
+                .saturating_add(Self::delegated_votes(&delegator, &proposal_type));
+            let capital =
+                balance.saturating_add(Self::delegated_capital(&delegator, &proposal_type));
+            Self::move_delegated_votes(&delegator, &expert, &proposal_type, votes, capital, true);
+
+            Self::deposit_event(Event::Delegated(delegator, expert, proposal_type, votes));
+
+            Ok(())
+        }
+
+        /// Withdraw the delegation of votes on proposals of `proposal_type`. The delegated
+        /// balance stays locked for the lock periods of its conviction.
+        #[pallet::call_index(11)]
+        #[pallet::weight(T::WeightInfo::undelegate(
+            T::MaxDelegationDepth::get(),
+            T::MaxActiveProposals::get(),
+        ))]
+        pub fn undelegate(origin: OriginFor<T>, proposal_type: ProposalType) -> DispatchResult {
+            let delegator = ensure_signed(origin)?;
+
+            let delegation = <Delegations<T>>::take(&delegator, &proposal_type)
+                .ok_or(Error::<T>::NotDelegating)?;
+
+            let votes = delegation
+                .conviction
+                .votes(delegation.balance)
+                .saturating_add(Self::delegated_votes(&delegator, &proposal_type));
+            let capital = delegation
+                .balance
+                .saturating_add(Self::delegated_capital(&delegator, &proposal_type));
+            Self::move_delegated_votes(
+                &delegator,
+                &delegation.expert,
+                &proposal_type,
+                votes,
+                capital,
+                false,
+            );
+
+            let current_block = <frame_system::Pallet<T>>::block_number();
+            Self::lock_stake(
+                &delegator,
+                delegation.balance,
+                current_block,
+                delegation.conviction,
+            );
+
+            Self::deposit_event(Event::Undelegated(delegator, proposal_type));
+
+            Ok(())
+        }
+    }
+
+    #[pallet::inherent]
+    impl<T: Config> ProvideInherent for Pallet<T> {
+        type Call = Call<T>;
+        type Error = poh::InherentError;
+        const INHERENT_IDENTIFIER: InherentIdentifier = poh::INHERENT_IDENTIFIER;
+
+        fn create_inherent(data: &InherentData) -> Option<Self::Call> {
+            let poh = data
+                .get_data::<PohDigest>(&poh::INHERENT_IDENTIFIER)
+                .ok()
+                .flatten()?;
+            Some(Call::set_poh { poh })
+        }
+
+        fn check_inherent(call: &Self::Call, _: &InherentData) -> Result<(), Self::Error> {
+            // The sequence itself is checked against the parent hash on dispatch
+            match call {
+                Call::set_poh { poh } if poh.ticks > T::MaxPohTicks::get() => {
+                    Err(poh::InherentError::TooManyTicks)
+                }
+                _ => Ok(()),
+            }
+        }
+
+        fn is_inherent_required(_: &InherentData) -> Result<Option<Self::Error>, Self::Error> {
+            // Every block must carry a sequence, so no block can skip the ordering proof
+            Ok(Some(poh::InherentError::Missing))
+        }
+
+        fn is_inherent(call: &Self::Call) -> bool {
+            matches!(call, Call::set_poh { .. })
+        }
     }
     
     // Implementation of pallet functions
     impl<T: Config> Pallet<T> {
-        // Calculate the current block reward
-        fn calculate_reward() -> Result<BalanceOf<T>, DispatchError> {
-            let current_reward = Self::current_block_reward();
-            let total_supply = Self::total_supply();
-            let max_supply = T::MaxSupply::get();
-            
-            // Check if maximum supply has been reached
-            if total_supply >= max_supply {
-                // Emit max supply reached event
-                let current_block = <frame_system::Pallet<T>>::block_number();
-                Self::deposit_event(Event::MaxSupplyReached(current_block, total_supply));
-                return
+        /// Median timestamp of the last `MedianTimeSpan` recorded blocks, if any were recorded.
+        pub fn median_time_past() -> Option<u64> {
+            let blocks = Self::recent_blocks();
+            let span = (T::MedianTimeSpan::get() as usize).min(blocks.len());
+            if span == 0 {
+                return None;
+            }
+
+            let mut timestamps: Vec<u64> = blocks[blocks.len() - span..]
+                .iter()
+                .map(|block| block.timestamp)
+                .collect();
+            timestamps.sort_unstable();
+
+            Some(timestamps[span / 2])
+        }
+
+        /// Difficulty the next block must be mined at, exposed through `PowApi`.
+        ///
+        /// Never returns zero, since a zero difficulty would accept any work hash.
+        pub fn difficulty() -> U256 {
+            Self::current_difficulty().max(U256::one())
+        }
+
+        /// Work function parameters block `number` is mined with.
+        pub fn pow_params_at(number: BlockNumberFor<T>) -> PowParams {
+            Self::pow_schedule().params_at(number.saturated_into())
+        }
+
+        /// Work function parameters the next block is mined with, exposed through
+        /// `CivicPowApi`.
+        pub fn pow_params() -> PowParams {
+            let next = <frame_system::Pallet<T>>::block_number().saturating_add(One::one());
+            Self::pow_params_at(next)
+        }
+
+        /// Schedule `params` from block `activation` on, at least `MinPowParamsDelay`
+        /// blocks ahead.
+        pub fn do_schedule_pow_params(
+            activation: BlockNumberFor<T>,
+            params: PowParams,
+        ) -> DispatchResult {
+            ensure!(params.is_valid(), Error::<T>::InvalidPowParams);
+
+            let current_block = <frame_system::Pallet<T>>::block_number();
+            let delay = T::MinPowParamsDelay::get().max(1);
+            let earliest = current_block.saturating_add(delay.saturated_into());
+            ensure!(
+                activation >= earliest,
+                Error::<T>::PowParamsActivationTooSoon
+            );
+
+            <PowParamsSchedule<T>>::mutate(|schedule| {
+                schedule.insert(activation.saturated_into(), params.clone())
+            });
+
+            Self::deposit_event(Event::PowParamsScheduled(activation, params));
+
+            Ok(())
+        }
+
+        /// Blocks between halvings, as set by governance or `BlocksPerYear * HalvingYears`.
+        pub fn halving_period() -> BlockNumberFor<T> {
+            <HalvingPeriod<T>>::get().unwrap_or_else(|| {
+                T::BlocksPerYear::get()
+                    .saturating_mul(T::HalvingYears::get())
+                    .saturated_into()
+            })
+        }
+
+        /// Target spacing between blocks in milliseconds, as set by governance or
+        /// `TargetBlockTime`, exposed through `CivicPowApi`.
+        pub fn target_block_time() -> u64 {
+            <BlockTimeTarget<T>>::get().unwrap_or_else(T::TargetBlockTime::get)
+        }
+
+        /// Coins still to be minted before `MaxSupply` is reached.
+        pub fn remaining_emission() -> BalanceOf<T> {
+            T::MaxSupply::get().saturating_sub(Self::total_supply())
+        }
+
+        // Lock `amount` of `who`'s balance for the lock periods of `conviction` after
+        // `voting_ends_at`
+        fn lock_stake(
+            who: &T::AccountId,
+            amount: BalanceOf<T>,
+            voting_ends_at: BlockNumberFor<T>,
+            conviction: Conviction,
+        ) {
+            let lock_period = T::StakeLockPeriod::get().saturating_mul(conviction.lock_periods());
+            let release = voting_ends_at.saturating_add(lock_period.saturated_into());
+            let (amount, release) = <LockedStakes<T>>::mutate(who, |locked| {
+                let (locked_amount, locked_release) =
+                    locked.get_or_insert((BalanceOf::<T>::zero(), release));
+                *locked_amount = (*locked_amount).max(amount);
+                *locked_release = (*locked_release).max(release);
+                (*locked_amount, *locked_release)
+            });
+
+            T::Currency::set_lock(GOVERNANCE_LOCK_ID, who, amount, WithdrawReasons::all());
+            Self::deposit_event(Event::StakeLocked(who.clone(), amount, release));
+        }
+
+        // Whether `who` voted on an active proposal of type `proposal_type`
+        fn has_active_vote(who: &T::AccountId, proposal_type: &ProposalType) -> bool {
+            Self::active_proposals().into_iter().any(|id| {
+                <Votes<T>>::contains_key(id, who)
+                    && Self::proposals(id)
+                        .map_or(false, |proposal| &proposal.proposal_type == proposal_type)
+            })
+        }
+
+        // Add `votes` delegated by `delegator` to `delegate` on `proposal_type` and the
+        // `capital` behind them, or remove them, along the delegations from `delegate` and on
+        // the active votes of the account they end at
+        fn move_delegated_votes(
+            delegator: &T::AccountId,
+            delegate: &T::AccountId,
+            proposal_type: &ProposalType,
+            votes: BalanceOf<T>,
+            capital: BalanceOf<T>,
+            add: bool,
+        ) {
+            let apply = |value: BalanceOf<T>, amount: BalanceOf<T>| {
+                if add {
+                    value.saturating_add(amount)
+                } else {
+                    value.saturating_sub(amount)
+                }
+            };
+
+            let mut depth = Self::delegation_depth(delegator, proposal_type).saturating_add(1);
+            let mut voter = delegate.clone();
+            loop {
+                <DelegatedVotes<T>>::mutate(&voter, proposal_type, |delegated| {
+                    *delegated = apply(*delegated, votes)
+                });
+                let remaining = <DelegatedCapital<T>>::mutate(&voter, proposal_type, |delegated| {
+                    *delegated = apply(*delegated, capital);
+                    *delegated
+                });
+                if add {
+                    <DelegationDepth<T>>::mutate(&voter, proposal_type, |known| {
+                        *known = (*known).max(depth)
+                    });
+                } else if remaining.is_zero() {
+                    <DelegationDepth<T>>::remove(&voter, proposal_type);
+                }
+                match Self::delegations(&voter, proposal_type) {
+                    Some(delegation) => {
+                        voter = delegation.expert;
+                        depth = depth.saturating_add(1);
+                    }
+                    None => break,
+                }
+            }
+
+            for id in Self::active_proposals() {
+                let mut proposal = match Self::proposals(id) {
+                    Some(proposal) if &proposal.proposal_type == proposal_type => proposal,
+                    _ => continue,
+                };
+                let mut vote = match Self::votes(id, &voter) {
+                    Some(vote) => vote,
+                    None => continue,
+                };
+
+                vote.delegated_votes = apply(vote.delegated_votes, votes);
+                vote.delegated_capital = apply(vote.delegated_capital, capital);
+                if vote.in_favor {
+                    proposal.votes_for = apply(proposal.votes_for, votes);
+                } else {
+                    proposal.votes_against = apply(proposal.votes_against, votes);
+                }
+                proposal.turnout = apply(proposal.turnout, capital);
+                <Votes<T>>::insert(id, &voter, vote);
+                <Proposals<T>>::insert(id, proposal);
+            }
+        }
+
+        // Whether an active proposal may change the halving period, which holds halvings back
+        fn has_active_halving_proposal() -> bool {
+            Self::active_proposals().iter().any(|id| {
+                Self::proposals(id).map_or(false, |proposal| {
+                    proposal.proposal_type == ProposalType::HalvingPeriod
+                })
+            })
+        }
+
+        // Whether `value` can be applied by a proposal of type `proposal_type`
+        fn is_valid_proposed_value(proposal_type: &ProposalType, value: u128) -> bool {
+            match proposal_type {
+                ProposalType::BlockReward => true,
+                _ => value > 0 && value <= u32::MAX as u128,
+            }
+        }
+
+        // Tally proposals whose voting ended before block `now`, and apply the approved ones.
+        // Returns the number of active proposals looked at.
+        fn finalize_proposals(now: BlockNumberFor<T>) -> u32 {
+            let mut active = Self::active_proposals();
+            let count = active.len() as u32;
+            let mut ended = Vec::new();
+            active.retain(|id| {
+                let open =
+                    Self::proposals(id).map_or(false, |proposal| proposal.voting_ends_at >= now);
+                if !open {
+                    ended.push(*id);
+                }
+                open
+            });
+            if ended.is_empty() {
+                return count;
+            }
+            <ActiveProposals<T>>::put(active);
+
+            for id in ended {
+                let mut proposal = match Self::proposals(id) {
+                    Some(proposal) => proposal,
+                    None => continue,
+                };
+
+                // The deposit is returned whatever the outcome
+                T::Currency::unreserve(&proposal.proposer, proposal.deposit);
+                <OpenProposals<T>>::mutate_exists(&proposal.proposer, |open| {
+                    let remaining = open.unwrap_or_default().saturating_sub(1);
+                    *open = (remaining > 0).then_some(remaining);
+                });
+
+                if !Self::is_approved(&proposal) {
+                    proposal.status = ProposalStatus::Rejected;
+                    Self::deposit_event(Event::ProposalRejected(
+                        id,
+                        proposal.votes_for,
+                        proposal.votes_against,
+                    ));
+                } else {
+                    proposal.status = ProposalStatus::Approved;
+                    Self::deposit_event(Event::ProposalApproved(
+                        id,
+                        proposal.votes_for,
+                        proposal.votes_against,
+                    ));
+
+                    // A proposal that cannot be applied stays approved, without effect
+                    if Self::execute_proposal(&proposal).is_ok() {
+                        proposal.status = ProposalStatus::Executed;
+                        Self::deposit_event(Event::ProposalExecuted(
+                            id,
+                            proposal.proposal_type.clone(),
+                            proposal.proposed_value,
+                        ));
+                    }
+                }
+
+                <Proposals<T>>::insert(id, proposal);
+            }
+
+            count
+        }
+
+        // Whether the votes on `proposal` approve it. Turnout, the capital behind the votes,
+        // must reach the quorum, the votes in favor must reach the threshold of the proposal
+        // type, and with adaptive quorum biasing the lower the turnout, the larger the
+        // majority needed:
+        //
+        // against / sqrt(turnout) < for / sqrt(electorate)
+        fn is_approved(proposal: &Proposal<BlockNumberFor<T>, T::AccountId, BalanceOf<T>>) -> bool {
+            let votes_for = proposal.votes_for;
+            let votes_against = proposal.votes_against;
+            let turnout = proposal.turnout;
+            if turnout.is_zero() || votes_for <= votes_against {
+                return false;
+            }
+
+            let total_issuance = T::Currency::total_issuance();
+            if turnout < T::Quorum::get().mul_ceil(total_issuance) {
+                return false;
+            }
+
+            let threshold = T::ApprovalThreshold::convert(proposal.proposal_type.clone());
+            if Perbill::from_rational(votes_for, votes_for.saturating_add(votes_against))
+                < threshold
+            {
+                return false;
+            }
+
+            let electorate: u128 = total_issuance.saturated_into();
+            let turnout: u128 = turnout.saturated_into();
+            let votes_for: u128 = votes_for.saturated_into();
+            let votes_against: u128 = votes_against.saturated_into();
+            U256::from(votes_against).saturating_mul(U256::from(electorate.integer_sqrt()))
+                < U256::from(votes_for).saturating_mul(U256::from(turnout.integer_sqrt()))
+        }
+
+        // Apply the change an approved proposal votes for
+        fn execute_proposal(
+            proposal: &Proposal<BlockNumberFor<T>, T::AccountId, BalanceOf<T>>,
+        ) -> DispatchResult {
+            let value = proposal.proposed_value;
+            ensure!(
+                Self::is_valid_proposed_value(&proposal.proposal_type, value),
+                Error::<T>::InvalidProposedValue
+            );
+
+            match proposal.proposal_type {
+                ProposalType::BlockReward => {
+                    <CurrentBlockReward<T>>::put(value.saturated_into::<BalanceOf<T>>());
+                }
+                // The new period counts from the last halving
+                ProposalType::HalvingPeriod => {
+                    <HalvingPeriod<T>>::put(value.saturated_into::<BlockNumberFor<T>>());
+                }
+                ProposalType::DifficultyAdjustment => {
+                    <BlockTimeTarget<T>>::put(value as u64);
+                }
+                ProposalType::ProtocolUpgrade => {
+                    let current_block = <frame_system::Pallet<T>>::block_number();
+                    let activation = current_block
+                        .saturating_add(T::MinPowParamsDelay::get().max(1).saturated_into());
+                    let params = PowParams {
+                        personalization: (PROTOCOL_UPGRADE_PREFIX, value as u32).encode(),
+                    };
+                    Self::do_schedule_pow_params(activation, params)?;
+                }
+            }
+
+            Ok(())
+        }
+
+        // Calculate the current block reward. The last reward is clipped so that the
+        // total supply lands exactly on the maximum supply.
+        pub(crate) fn calculate_reward() -> BalanceOf<T> {
+            if Self::fees_only() {
+                return BalanceOf::<T>::zero();
+            }
+
+            Self::current_block_reward().min(Self::remaining_emission())
+        }
+
+        // Hash of the parent block, as committed to by PoH and the MMR
+        fn parent_hash() -> H256 {
+            let parent_hash = <frame_system::Pallet<T>>::parent_hash();
+            H256::decode(&mut parent_hash.as_ref()).unwrap_or_default()
+        }
+
+        // Append the parent block to the MMR and commit to the new root in the header.
+        // The parent is the last recorded block; the genesis block has no difficulty.
+        fn update_mmr() {
+            let difficulty = Self::recent_blocks()
+                .last()
+                .map(|block| block.difficulty)
+                .unwrap_or_default();
+            let leaf = MmrNode::leaf(Self::parent_hash(), difficulty);
+
+            let commitment = <Mmr<T>>::mutate(|mmr| {
+                mmr.push(leaf);
+                mmr.commitment()
+            });
+            if let Some(commitment) = commitment {
+                <frame_system::Pallet<T>>::deposit_log(commitment.to_digest_item());
+            }
+        }
+
+        // Author of the current block, taken from the pre-runtime digest the miner sealed
+        fn find_author() -> Option<T::AccountId> {
+            let digest = <frame_system::Pallet<T>>::digest();
+            let pre_runtime_digests = digest.logs.iter().filter_map(|item| item.as_pre_runtime());
+            T::FindAuthor::find_author(pre_runtime_digests)
+        }
+
+        // Mint up to `amount` to `who`, never past the maximum supply, and return what was minted
+        fn issue_reward(who: &T::AccountId, amount: BalanceOf<T>) -> BalanceOf<T> {
+            let amount = if Self::fees_only() {
+                BalanceOf::<T>::zero()
+            } else {
+                amount.min(Self::remaining_emission())
+            };
+            if amount.is_zero() {
+                return amount;
+            }
+
+            // The positive imbalance raises total issuance when dropped
+            let minted = T::Currency::deposit_creating(who, amount).peek();
+            <TotalSupply<T>>::mutate(|supply| *supply = supply.saturating_add(minted));
+            minted
+        }
+
+        // Mint the block reward to the author of the current block. Blocks without an
+        // author digest are not rewarded.
+        fn reward_author(number: BlockNumberFor<T>) {
+            let reward = Self::calculate_reward();
+
+            if let Some(author) = Self::author().filter(|_| !reward.is_zero()) {
+                let minted = Self::issue_reward(&author, reward);
+
+                if !minted.is_zero() {
+                    Self::deposit_event(Event::RewardPaid(author, minted, number));
+                }
+            }
+
+            // Once everything is minted, miners are paid with transaction fees only
+            if !Self::fees_only() && Self::remaining_emission().is_zero() {
+                <FeesOnly<T>>::put(true);
+                Self::deposit_event(Event::MaxSupplyReached(number, Self::total_supply()));
+            }
+        }
+
+        // Check that `uncle` is a sealed stale block that may be included at block `now`,
+        // returning its record and author. Cheap checks run before the seal is hashed.
+        fn verify_uncle(
+            now: BlockNumberFor<T>,
+            mut uncle: HeaderFor<T>,
+        ) -> Result<(UncleInfo<BlockNumberFor<T>, T::Hash>, T::AccountId), DispatchError> {
+            let number = *uncle.number();
+            ensure!(!number.is_zero() && number < now, Error::<T>::UncleNotStale);
+            ensure!(
+                now.saturating_sub(number) <= T::MaxUncleDepth::get().saturated_into(),
+                Error::<T>::UncleTooOld
+            );
+
+            let uncles = Self::uncles();
+            ensure!(
+                uncles.iter().filter(|uncle| uncle.included_in == now).count()
+                    < T::MaxUncles::get() as usize,
+                Error::<T>::TooManyUncles
+            );
+
+            let hash = uncle.hash();
+            ensure!(
+                !uncles.iter().any(|uncle| uncle.hash == hash),
+                Error::<T>::UncleAlreadyIncluded
+            );
+
+            let block_hash = <frame_system::Pallet<T>>::block_hash;
+            ensure!(block_hash(number) != hash, Error::<T>::UncleNotStale);
+            ensure!(
+                block_hash(number.saturating_sub(One::one())) == *uncle.parent_hash(),
+                Error::<T>::UncleParentNotInChain
+            );
+
+            let author = T::FindAuthor::find_author(
+                uncle.digest().logs.iter().filter_map(|item| item.as_pre_runtime()),
+            )
+            .ok_or(Error::<T>::UncleAuthorNotFound)?;
+
+            // Every check above is cheap. The Yespower hash below, which `include_uncle` is
+            // weighted for, only runs once they have all passed.
+
+            // Siblings share a parent, so the uncle had to meet the difficulty of the block
+            // recorded at its height
+            let difficulty = Self::recent_blocks()
+                .iter()
+                .find(|block| block.number == number)
+                .map(|block| block.difficulty)
+                .ok_or(Error::<T>::UncleTooOld)?;
+
+            // The seal is the last digest item and is not covered by the pre-hash
+            let seal = match uncle.digest_mut().pop() {
+                Some(DigestItem::Seal(id, seal)) if id == POW_ENGINE_ID => {
+                    CivicSeal::decode_raw(&seal).ok_or(Error::<T>::InvalidUncleSeal)?
+                }
+                _ => return Err(Error::<T>::InvalidUncleSeal.into()),
+            };
+            ensure!(
+                seal.difficulty == difficulty
+                    && seal.follows_poh(uncle.digest())
+                    && seal.verify(&Self::pow_params_at(number), uncle.hash().as_ref()),
+                Error::<T>::InvalidUncleSeal
+            );
+
+            Ok((UncleInfo { number, hash, included_in: now }, author))
+        }
+
+        // Append the current block to the difficulty window, dropping the oldest entries
+        fn record_block(number: BlockNumberFor<T>) {
+            // Difficulty has not been retargeted yet, so this is what the block was mined at
+            let difficulty = Self::difficulty();
+            let total_difficulty = Self::total_difficulty().saturating_add(difficulty);
+            <TotalDifficulty<T>>::put(total_difficulty);
+
+            <RecentBlocks<T>>::mutate(|blocks| {
+                blocks.push(BlockInfo {
+                    number,
+                    hash: Default::default(),
+                    parent_hash: <frame_system::Pallet<T>>::parent_hash(),
+                    timestamp: T::Time::now(),
+                    author: Self::author(),
+                    difficulty,
+                    total_difficulty,
+                    poh_hash: Self::last_poh_hash(),
+                });
+
+                // One extra entry is kept so the window holds `DifficultyWindow` solve times
+                let window = T::DifficultyWindow::get() as usize + 1;
+                if blocks.len() > window {
+                    blocks.drain(..blocks.len() - window);
+                }
+            });
+        }
+
+        // Retarget difficulty every block from the recorded window
+        fn adjust_difficulty() {
+            if let Some(new_difficulty) = Self::next_difficulty(&Self::recent_blocks()) {
+                <CurrentDifficulty<T>>::put(new_difficulty);
+            }
+        }
+
+        // Linearly weighted moving average (LWMA) of solve times: recent blocks weigh more,
+        // so the chain reacts within a few blocks to hashrate swings.
+        //
+        // next = avg_difficulty * target * k / sum(i * solve_time_i), with k = n(n+1)/2
+        fn next_difficulty(blocks: &[RecordedBlockOf<T>]) -> Option<U256> {
+            if blocks.len() < 2 {
+                return None;
+            }
+
+            let target = Self::target_block_time();
+            let max_solve_time = target.saturating_mul(MAX_SOLVE_TIME_FACTOR);
+
+            let mut previous = blocks[0].timestamp;
+            let mut weighted_solve_times: u128 = 0;
+            let mut sum_difficulty = U256::zero();
+
+            for (i, block) in blocks.iter().enumerate().skip(1) {
+                // A timestamp earlier than its predecessor counts as a zero solve time
+                let timestamp = block.timestamp.max(previous);
+                let solve_time = timestamp.saturating_sub(previous).min(max_solve_time);
+                previous = timestamp;
+
+                weighted_solve_times =
+                    weighted_solve_times.saturating_add((i as u128).saturating_mul(solve_time as u128));
+                sum_difficulty = sum_difficulty.saturating_add(block.difficulty);
+            }
+
+            let n = (blocks.len() - 1) as u128;
+            let k = n.saturating_mul(n + 1) / 2;
+
+            let next = sum_difficulty
+                .saturating_mul(U256::from(target))
+                .saturating_mul(U256::from(k))
+                / U256::from(n.saturating_mul(weighted_solve_times.max(1)));
+
+            // Bound the change relative to the current difficulty
+            let current = Self::current_difficulty();
+            let factor = U256::from(T::DifficultyClampFactor::get().max(1));
+            let next = next.clamp(current / factor, current.saturating_mul(factor));
+
+            Some(next.max(U256::one()))
+        }
+    }
+
+    /// Approval thresholds by proposal type: changes to the emission schedule and the work
+    /// function need two thirds of the votes in favor, others a simple majority.
+    pub struct DefaultApprovalThresholds;
+
+    impl Convert<ProposalType, Perbill> for DefaultApprovalThresholds {
+        fn convert(proposal_type: ProposalType) -> Perbill {
+            match proposal_type {
+                ProposalType::HalvingPeriod | ProposalType::ProtocolUpgrade => {
+                    Perbill::from_rational(2u32, 3u32)
+                }
+                ProposalType::BlockReward | ProposalType::DifficultyAdjustment => {
+                    Perbill::from_percent(50)
+                }
+            }
+        }
+    }
+
+    /// Transaction fee handler. Fees are burned while block rewards are minted, and paid
+    /// to the block author once the pallet is in fees-only mode.
+    pub struct FeesToAuthor<T>(PhantomData<T>);
+
+    impl<T: Config> OnUnbalanced<NegativeImbalanceOf<T>> for FeesToAuthor<T> {
+        fn on_nonzero_unbalanced(fees: NegativeImbalanceOf<T>) {
+            if !Pallet::<T>::fees_only() {
+                return;
+            }
+
+            // Without an author the fees are burned, as they are before fees-only mode
+            if let Some(author) = Pallet::<T>::author() {
+                T::Currency::resolve_creating(&author, fees);
+            }
+        }
+    }
+
+    // Median-time-past rule: a block may not be timestamped at or before the median of
+    // recent blocks, so miners cannot backdate blocks to drag difficulty down.
+    // Timestamps more than 30 seconds ahead of the importing node's clock are rejected
+    // by `pallet_timestamp`'s inherent check.
+    impl<T: Config> OnTimestampSet<u64> for Pallet<T> {
+        fn on_timestamp_set(now: u64) {
+            if let Some(median) = Self::median_time_past() {
+                assert!(now > median, "Timestamp must be greater than the median time past");
+            }
+        }
+    }
+}
@@ -1,12 +1,16 @@
 use crate as civicchain_pow;
+use civicchain_pow::{compute_work, meets_difficulty, CivicSeal, PohDigest, PowParams};
 use frame_support::{
     parameter_types,
     traits::{ConstU32, ConstU64, OnFinalize, OnInitialize},
 };
+use frame_system::pallet_prelude::HeaderFor;
+use parity_scale_codec::Encode;
+use sp_consensus_pow::POW_ENGINE_ID;
 use sp_core::{H256, U256};
 use sp_runtime::{
-    traits::{BlakeTwo256, IdentityLookup},
-    BuildStorage,
+    traits::{BlakeTwo256, Header as _, IdentityLookup},
+    BuildStorage, Digest, DigestItem, Perbill,
 };
 
 type Block = frame_system::mocking::MockBlock<Test>;
@@ -17,6 +21,7 @@ frame_support::construct_runtime!(
     {
         System: frame_system,
         Balances: pallet_balances,
+        Timestamp: pallet_timestamp,
         PowPallet: civicchain_pow,
     }
 );
@@ -54,7 +59,7 @@ impl pallet_balances::Config for Test {
     type ExistentialDeposit = ConstU64<1>;
     type AccountStore = System;
     type WeightInfo = ();
-    type MaxLocks = ();
+    type MaxLocks = ConstU32<50>;
     type MaxReserves = ();
     type ReserveIdentifier = [u8; 8];
     type RuntimeHoldReason = ();
@@ -63,21 +68,64 @@ impl pallet_balances::Config for Test {
     type MaxFreezes = ();
 }
 
+impl pallet_timestamp::Config for Test {
+    type Moment = u64;
+    type OnTimestampSet = PowPallet;
+    type MinimumPeriod = ConstU64<1>;
+    type WeightInfo = ();
+}
+
 parameter_types! {
-    pub const BlockReward: u64 = 60;
-    pub const BlocksPerYear: u32 = 2_628_000; // ~5 segundos por bloco, 365 dias
+    // The emission schedule can be changed per test with `new_test_ext_with_emission`
+    pub static BlockReward: u64 = 60;
+    pub static BlocksPerYear: u32 = 2_628_000; // ~5 segundos por bloco, 365 dias
     pub const HalvingYears: u32 = 5;
-    pub const MaxSupply: u64 = 29_000_000;
+    pub static MaxSupply: u64 = 29_000_000;
+    pub const DifficultyWindow: u32 = 60;
+    pub const TargetBlockTime: u64 = 200_000;
+    pub const DifficultyClampFactor: u32 = 2;
+    pub const MedianTimeSpan: u32 = 11;
+    pub const MaxUncleDepth: u32 = 6;
+    pub const MaxUncles: u32 = 2;
+    pub const MaxPohTicks: u64 = 1_000;
+    pub const MinPowParamsDelay: u32 = 10;
+    pub const MinProposalStake: u64 = 5;
+    pub const ProposalDeposit: u64 = 2;
+    pub const MaxActiveProposals: u32 = 5;
+    pub const MaxProposalsPerAccount: u32 = 3;
+    pub const MaxDelegationDepth: u32 = 3;
+    pub const StakeLockPeriod: u32 = 3;
+    pub const MaxVotingPeriod: u32 = 20;
+    pub const Quorum: Perbill = Perbill::from_percent(10);
 }
 
 impl civicchain_pow::Config for Test {
     type RuntimeEvent = RuntimeEvent;
     type Currency = Balances;
-    type RewardHandler = ();
+    type FindAuthor = civicchain_pow::PowDigestAuthor<u64>;
     type BlockReward = BlockReward;
     type BlocksPerYear = BlocksPerYear;
     type HalvingYears = HalvingYears;
     type MaxSupply = MaxSupply;
+    type Time = Timestamp;
+    type DifficultyWindow = DifficultyWindow;
+    type TargetBlockTime = TargetBlockTime;
+    type DifficultyClampFactor = DifficultyClampFactor;
+    type MedianTimeSpan = MedianTimeSpan;
+    type MaxUncleDepth = MaxUncleDepth;
+    type MaxUncles = MaxUncles;
+    type MaxPohTicks = MaxPohTicks;
+    type MinPowParamsDelay = MinPowParamsDelay;
+    type MinProposalStake = MinProposalStake;
+    type ProposalDeposit = ProposalDeposit;
+    type MaxActiveProposals = MaxActiveProposals;
+    type MaxProposalsPerAccount = MaxProposalsPerAccount;
+    type MaxDelegationDepth = MaxDelegationDepth;
+    type StakeLockPeriod = StakeLockPeriod;
+    type MaxVotingPeriod = MaxVotingPeriod;
+    type Quorum = Quorum;
+    type ApprovalThreshold = civicchain_pow::DefaultApprovalThresholds;
+    type WeightInfo = ();
 }
 
 // Build genesis storage according to the mock runtime.
@@ -101,14 +149,98 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
     t.into()
 }
 
+// Like `new_test_ext`, paying `block_reward` per block, halving every `blocks_per_year *
+// HalvingYears` blocks, up to `max_supply`.
+pub fn new_test_ext_with_emission(
+    block_reward: u64,
+    blocks_per_year: u32,
+    max_supply: u64,
+) -> sp_io::TestExternalities {
+    BlockReward::set(&block_reward);
+    BlocksPerYear::set(&blocks_per_year);
+    MaxSupply::set(&max_supply);
+    new_test_ext()
+}
+
 pub fn run_to_block(n: u64) {
+    run_to_block_with_spacing(n, TargetBlockTime::get());
+}
+
+// Advance blocks, setting each block's timestamp `spacing` milliseconds after the previous one.
+pub fn run_to_block_with_spacing(n: u64, spacing: u64) {
     while System::block_number() < n {
-        if System::block_number() > 0 {
-            PowPallet::on_finalize(System::block_number());
-            System::on_finalize(System::block_number());
-        }
-        System::set_block_number(System::block_number() + 1);
-        System::on_initialize(System::block_number());
-        PowPallet::on_initialize(System::block_number());
+        next_block();
+        Timestamp::set_timestamp(Timestamp::now() + spacing);
+    }
+}
+
+// Finalize the current block and initialize the next one, leaving its timestamp unset.
+pub fn next_block() {
+    next_block_with_digest(Digest::default());
+}
+
+// Like `next_block`, but the new block carries `author` in its pre-runtime digest.
+pub fn next_block_authored_by(author: u64) {
+    next_block_with_digest(Digest {
+        logs: vec![DigestItem::PreRuntime(POW_ENGINE_ID, author.encode())],
+    });
+}
+
+pub fn next_block_with_digest(digest: Digest) {
+    if System::block_number() > 0 {
+        PowPallet::on_finalize(System::block_number());
+        System::on_finalize(System::block_number());
     }
+    let number = System::block_number() + 1;
+    System::initialize(&number, &H256::from_low_u64_be(number - 1), &digest);
+    System::on_initialize(number);
+    PowPallet::on_initialize(number);
+}
+
+// Header at `number` on top of `parent`, not yet sealed.
+pub fn uncle_header(number: u64, parent: H256, digest: Digest) -> HeaderFor<Test> {
+    HeaderFor::<Test>::new(number, H256::zero(), H256::zero(), parent, digest)
+}
+
+// Seal `header` with the first nonce whose work meets `difficulty`, after giving it an
+// empty PoH sequence.
+pub fn seal_header(header: HeaderFor<Test>, difficulty: U256) -> HeaderFor<Test> {
+    seal_header_with_params(header, difficulty, &PowParams::default())
+}
+
+// Like `seal_header`, hashing with the work function parameters `params`.
+pub fn seal_header_with_params(
+    mut header: HeaderFor<Test>,
+    difficulty: U256,
+    params: &PowParams,
+) -> HeaderFor<Test> {
+    let poh = PohDigest::new(*header.parent_hash());
+    header.digest_mut().push(poh.to_digest_item());
+
+    let pre_hash = header.hash();
+    let seal = (0u64..)
+        .find_map(|nonce| {
+            let nonce = nonce.to_le_bytes();
+            let work = compute_work(params, pre_hash.as_bytes(), &nonce);
+            meets_difficulty(&work, difficulty)
+                .then(|| CivicSeal::new(difficulty, nonce, poh.hash, work))
+        })
+        .unwrap();
+
+    header
+        .digest_mut()
+        .push(DigestItem::Seal(POW_ENGINE_ID, seal.encode()));
+    header
+}
+
+// A stale block at `number` mined by `author` on top of the canonical block below it.
+pub fn mine_uncle(number: u64, author: u64) -> HeaderFor<Test> {
+    let digest = Digest {
+        logs: vec![DigestItem::PreRuntime(POW_ENGINE_ID, author.encode())],
+    };
+    seal_header_with_params(
+        uncle_header(number, H256::from_low_u64_be(number - 1), digest),
+        PowPallet::difficulty(),
+        &PowPallet::pow_params_at(number),
+    )
 }
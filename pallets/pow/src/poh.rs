@@ -0,0 +1,41 @@
+//! Proof-of-History (PoH) inherent.
+//!
+//! The sequence itself lives in [`civicchain_primitives::poh`] so light
+//! clients can check it without the runtime. The block producer hands its
+//! sequence to the runtime through the inherent defined here, and the runtime
+//! checks it with [`native_tick`].
+
+pub use civicchain_primitives::poh::*;
+
+#[cfg(feature = "std")]
+use parity_scale_codec::Decode;
+use parity_scale_codec::Encode;
+use sp_core::H256;
+use sp_inherents::{InherentIdentifier, IsFatalError};
+use sp_runtime::RuntimeDebug;
+
+/// Identifier of the inherent through which the block producer hands its PoH
+/// sequence to the runtime.
+pub const INHERENT_IDENTIFIER: InherentIdentifier = *b"civicpoh";
+
+/// Errors reported when checking the PoH inherent.
+#[derive(Encode, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Decode))]
+pub enum InherentError {
+    /// The block does not carry a PoH sequence.
+    Missing,
+    /// The sequence has more ticks than the runtime allows.
+    TooManyTicks,
+}
+
+impl IsFatalError for InherentError {
+    fn is_fatal_error(&self) -> bool {
+        true
+    }
+}
+
+/// Same as [`tick`], but hashed by the host, so the runtime runs each tick
+/// natively rather than as SHA-256 compiled to wasm.
+pub fn native_tick(hash: &H256) -> H256 {
+    H256(sp_io::hashing::sha2_256(hash.as_bytes()))
+}
@@ -0,0 +1,28 @@
+//! Runtime API exposing PoW pallet state to the node and RPC.
+
+use crate::{PowParams, PowSchedule};
+use parity_scale_codec::Codec;
+
+sp_api::decl_runtime_apis! {
+    /// CivicChain PoW runtime API.
+    pub trait CivicPowApi<AccountId, Balance>
+    where
+        AccountId: Codec,
+        Balance: Codec,
+    {
+        /// Author of the block this is called at, if the block carried an author digest.
+        fn author() -> Option<AccountId>;
+
+        /// Coins still to be minted as block rewards before the maximum supply is reached.
+        fn remaining_emission() -> Balance;
+
+        /// Work function parameters a block built on this one is mined with.
+        fn pow_params() -> PowParams;
+
+        /// Work function parameters by activation height, as scheduled so far.
+        fn pow_schedule() -> PowSchedule;
+
+        /// Target spacing between blocks in milliseconds, which governance may change.
+        fn target_block_time() -> u64;
+    }
+}
@@ -1,7 +1,23 @@
-use crate::{mock::*, Error, Event};
-use frame_support::{assert_noop, assert_ok};
+use crate::{
+    compute_work,
+    mmr::MmrNode,
+    mock::*,
+    poh,
+    yespower::{MAX_PERSONALIZATION_LEN, SEAL_VERSION},
+    Call, CivicSeal, Conviction, Error, Event, FeesToAuthor, MmrCommitment, PohDigest, PowParams,
+    PowSchedule, ProposalStatus, ProposalType, NONCE_SIZE,
+};
+use frame_support::{
+    assert_noop, assert_ok,
+    dispatch::{DispatchResult, GetDispatchInfo},
+    inherent::{InherentData, ProvideInherent},
+    traits::{Currency, ExistenceRequirement, Hooks, OnUnbalanced, WithdrawReasons},
+};
+use frame_system::pallet_prelude::HeaderFor;
+use parity_scale_codec::Encode;
+use sp_consensus_pow::POW_ENGINE_ID;
 use sp_core::{H256, U256};
-use sp_runtime::traits::{BlakeTwo256, Hash};
+use sp_runtime::{traits::Header as _, Digest, DigestItem};
 
 #[test]
 fn initial_state_is_correct() {
@@ -43,56 +59,753 @@ fn halving_works() {
     });
 }
 
+fn create_proposal(proposer: u64, proposal_type: ProposalType, value: u128) -> DispatchResult {
+    PowPallet::create_proposal(
+        RuntimeOrigin::signed(proposer),
+        proposal_type,
+        b"proposta".to_vec(),
+        value,
+        5,
+    )
+}
+
+#[test]
+fn approved_proposals_are_executed() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        // Uma proposta de cada tipo, com votação até o bloco 6
+        assert_ok!(create_proposal(1, ProposalType::BlockReward, 100));
+        System::assert_last_event(
+            Event::<Test>::ProposalCreated(1, 1, ProposalType::BlockReward).into(),
+        );
+        assert_ok!(create_proposal(2, ProposalType::HalvingPeriod, 20));
+        assert_ok!(create_proposal(
+            2,
+            ProposalType::DifficultyAdjustment,
+            100_000
+        ));
+        assert_ok!(create_proposal(2, ProposalType::ProtocolUpgrade, 2));
+        assert_eq!(
+            PowPallet::proposals(2).unwrap().proposal_type,
+            ProposalType::HalvingPeriod
+        );
+        assert_eq!(PowPallet::active_proposals(), vec![1, 2, 3, 4]);
+
+        // Nada muda enquanto a votação está aberta
+        run_to_block(6);
+        assert_eq!(PowPallet::current_block_reward(), 60);
+        assert_eq!(
+            PowPallet::proposals(1).unwrap().status,
+            ProposalStatus::Active
+        );
+
+        // No bloco seguinte ao fim da votação as propostas aprovadas são aplicadas
+        run_to_block(7);
+        assert!(PowPallet::active_proposals().is_empty());
+        for id in 1..=4 {
+            assert_eq!(
+                PowPallet::proposals(id).unwrap().status,
+                ProposalStatus::Executed
+            );
+        }
+        System::assert_has_event(Event::<Test>::ProposalApproved(1, 5, 0).into());
+        System::assert_has_event(
+            Event::<Test>::ProposalExecuted(1, ProposalType::BlockReward, 100).into(),
+        );
+        assert_eq!(PowPallet::current_block_reward(), 100);
+        assert_eq!(PowPallet::halving_period(), 20);
+        assert_eq!(PowPallet::target_block_time(), 100_000);
+
+        // A atualização de protocolo troca a personalização após `MinPowParamsDelay`
+        let upgrade = PowParams {
+            personalization: (*b"civicchain-protocol", 2u32).encode(),
+        };
+        System::assert_has_event(Event::<Test>::PowParamsScheduled(17, upgrade.clone()).into());
+        assert_eq!(PowPallet::pow_params_at(16), PowParams::default());
+        assert_eq!(PowPallet::pow_params_at(17), upgrade);
+
+        // O novo período de halving conta a partir do último halving
+        run_to_block(20);
+        assert_eq!(PowPallet::current_block_reward(), 50);
+        assert_eq!(PowPallet::last_halving_block(), 20);
+    });
+}
+
+#[test]
+fn rejected_proposals_have_no_effect() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        // Valores que o tipo da proposta não consegue aplicar são recusados
+        assert_noop!(
+            create_proposal(1, ProposalType::HalvingPeriod, 0),
+            Error::<Test>::InvalidProposedValue
+        );
+
+        // O proponente vota a favor com 5 e a conta 2 vota contra com 10
+        assert_ok!(create_proposal(1, ProposalType::BlockReward, 100));
+        assert_ok!(PowPallet::vote(
+            RuntimeOrigin::signed(2),
+            1,
+            false,
+            10,
+            Conviction::Locked1x
+        ));
+
+        run_to_block(7);
+        assert_eq!(
+            PowPallet::proposals(1).unwrap().status,
+            ProposalStatus::Rejected
+        );
+        System::assert_has_event(Event::<Test>::ProposalRejected(1, 5, 10).into());
+        assert_eq!(PowPallet::current_block_reward(), 60);
+        assert!(PowPallet::active_proposals().is_empty());
+    });
+}
+
+#[test]
+fn proposals_need_quorum_and_threshold() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        Balances::make_free_balance_be(&3, 2_000);
+        Balances::make_free_balance_be(&4, 2_000);
+
+        // O quórum conta todo o CVX emitido, inclusive o da gênese, que não está em
+        // `TotalSupply`
+        pallet_balances::TotalIssuance::<Test>::put(1_000);
+        assert_eq!(PowPallet::total_supply(), 0);
+        let vote = |voter, id, in_favor, stake| {
+            PowPallet::vote(
+                RuntimeOrigin::signed(voter),
+                id,
+                in_favor,
+                stake,
+                Conviction::Locked1x,
+            )
+        };
+
+        // 1: só o proponente vota, abaixo do quórum de 10% de 1000
+        assert_ok!(create_proposal(1, ProposalType::BlockReward, 100));
+
+        // 2: 70% a favor com todo o suprimento votando atinge os 2/3 exigidos
+        assert_ok!(create_proposal(2, ProposalType::ProtocolUpgrade, 2));
+        assert_ok!(vote(3, 2, true, 695));
+        assert_ok!(vote(4, 2, false, 300));
+
+        // 3: 60% a favor é maioria, mas não atinge os 2/3
+        assert_ok!(create_proposal(2, ProposalType::ProtocolUpgrade, 3));
+        assert_ok!(vote(3, 3, true, 595));
+        assert_ok!(vote(4, 3, false, 400));
+
+        // 4: 57% a favor com 35% de participação é pouco com o viés de quórum adaptativo
+        assert_ok!(create_proposal(2, ProposalType::BlockReward, 100));
+        assert_ok!(vote(3, 4, true, 195));
+        assert_ok!(vote(4, 4, false, 150));
+
+        // 5: 16 bloqueados com convicção 6x valem 96 votos, mas o quórum conta só o capital
+        assert_ok!(create_proposal(1, ProposalType::BlockReward, 80));
+        assert_ok!(PowPallet::vote(
+            RuntimeOrigin::signed(3),
+            5,
+            true,
+            16,
+            Conviction::Locked6x
+        ));
+        assert_eq!(PowPallet::proposals(5).unwrap().votes_for, 101);
+        assert_eq!(PowPallet::proposals(5).unwrap().turnout, 21);
+
+        run_to_block(7);
+        System::assert_has_event(Event::<Test>::ProposalRejected(1, 5, 0).into());
+        System::assert_has_event(
+            Event::<Test>::ProposalExecuted(2, ProposalType::ProtocolUpgrade, 2).into(),
+        );
+        System::assert_has_event(Event::<Test>::ProposalRejected(3, 600, 400).into());
+        System::assert_has_event(Event::<Test>::ProposalRejected(4, 200, 150).into());
+        System::assert_has_event(Event::<Test>::ProposalRejected(5, 101, 0).into());
+        assert_eq!(PowPallet::current_block_reward(), 60);
+    });
+}
+
+#[test]
+fn voting_period_is_bounded() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let create = |voting_period| {
+            PowPallet::create_proposal(
+                RuntimeOrigin::signed(1),
+                ProposalType::HalvingPeriod,
+                b"proposta".to_vec(),
+                20,
+                voting_period,
+            )
+        };
+
+        // Uma proposta de período de halving segura os halvings no máximo por
+        // `MaxVotingPeriod` blocos
+        let max = MaxVotingPeriod::get() as u64;
+        assert_noop!(create(max + 1), Error::<Test>::VotingPeriodTooLong);
+        assert_ok!(create(max));
+        assert_eq!(PowPallet::proposals(1).unwrap().voting_ends_at, 1 + max);
+    });
+}
+
+#[test]
+fn proposals_are_bounded() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        for account in [1, 2, 3] {
+            Balances::make_free_balance_be(&account, 100);
+        }
+
+        // Cada proposta reserva um depósito próprio, além do stake bloqueado
+        for value in 1..=3 {
+            assert_ok!(create_proposal(1, ProposalType::BlockReward, value));
+        }
+        assert_eq!(Balances::reserved_balance(1), 3 * ProposalDeposit::get());
+        assert_eq!(PowPallet::open_proposals(1), 3);
+
+        // Uma conta tem no máximo `MaxProposalsPerAccount` propostas abertas
+        assert_noop!(
+            create_proposal(1, ProposalType::BlockReward, 4),
+            Error::<Test>::TooManyProposals
+        );
+
+        // E todas as contas juntas no máximo `MaxActiveProposals`
+        assert_ok!(create_proposal(2, ProposalType::BlockReward, 4));
+        assert_ok!(create_proposal(2, ProposalType::BlockReward, 5));
+        assert_eq!(
+            PowPallet::active_proposals().len() as u32,
+            MaxActiveProposals::get()
+        );
+        assert_noop!(
+            create_proposal(3, ProposalType::BlockReward, 6),
+            Error::<Test>::TooManyActiveProposals
+        );
+
+        // Os depósitos voltam quando as propostas são apuradas
+        run_to_block(7);
+        assert_eq!(Balances::reserved_balance(1), 0);
+        assert_eq!(PowPallet::open_proposals(1), 0);
+        assert_ok!(create_proposal(3, ProposalType::BlockReward, 6));
+    });
+}
+
+#[test]
+fn halving_proposals_hold_halvings_back_for_a_bounded_time() {
+    // Halving a cada 10 blocos
+    new_test_ext_with_emission(60, 2, 29_000_000).execute_with(|| {
+        run_to_block(1);
+        let propose_and_reject = |voting_period| {
+            let id = PowPallet::next_proposal_id();
+            assert_ok!(PowPallet::create_proposal(
+                RuntimeOrigin::signed(1),
+                ProposalType::HalvingPeriod,
+                b"proposta".to_vec(),
+                20,
+                voting_period,
+            ));
+            assert_ok!(PowPallet::vote(
+                RuntimeOrigin::signed(2),
+                id,
+                false,
+                10,
+                Conviction::Locked1x
+            ));
+        };
+
+        // Propostas abertas uma depois da outra seguram o halving devido no bloco 10...
+        propose_and_reject(20);
+        run_to_block(15);
+        propose_and_reject(20);
+        run_to_block(29);
+        assert_eq!(PowPallet::current_block_reward(), 60);
+
+        // ...mas no máximo por `MaxVotingPeriod` blocos
+        run_to_block(30);
+        assert_eq!(PowPallet::active_proposals().len(), 1);
+        assert_eq!(PowPallet::current_block_reward(), 30);
+        assert_eq!(PowPallet::last_halving_block(), 30);
+    });
+}
+
+#[test]
+fn stakes_are_released_after_lock_period() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        // A votação termina no bloco 6 e os bloqueios ficam até o bloco 9
+        assert_ok!(create_proposal(1, ProposalType::BlockReward, 100));
+        System::assert_has_event(Event::<Test>::StakeLocked(1, 5, 9).into());
+        assert_ok!(PowPallet::vote(
+            RuntimeOrigin::signed(2),
+            1,
+            true,
+            10,
+            Conviction::Locked1x
+        ));
+        assert_eq!(PowPallet::locked_stakes(2), Some((10, 9)));
+        assert_eq!(Balances::usable_balance(2), 10);
+
+        run_to_block(8);
+        assert_noop!(
+            PowPallet::unlock_stake(RuntimeOrigin::signed(1)),
+            Error::<Test>::LockPeriodNotFinished
+        );
+
+        // Um novo bloqueio cobre os mesmos fundos e adia a liberação de tudo
+        assert_ok!(create_proposal(2, ProposalType::HalvingPeriod, 20));
+        assert_eq!(PowPallet::locked_stakes(2), Some((10, 16)));
+
+        run_to_block(9);
+        assert_ok!(PowPallet::unlock_stake(RuntimeOrigin::signed(1)));
+        System::assert_last_event(Event::<Test>::StakeUnlocked(1, 5).into());
+        assert_eq!(Balances::usable_balance(1), 10);
+        assert_eq!(PowPallet::locked_stakes(1), None);
+        assert_noop!(
+            PowPallet::unlock_stake(RuntimeOrigin::signed(1)),
+            Error::<Test>::NoStakeLocked
+        );
+        assert_noop!(
+            PowPallet::unlock_stake(RuntimeOrigin::signed(2)),
+            Error::<Test>::LockPeriodNotFinished
+        );
+
+        run_to_block(16);
+        assert_ok!(PowPallet::unlock_stake(RuntimeOrigin::signed(2)));
+        assert_eq!(Balances::usable_balance(2), 20);
+    });
+}
+
+#[test]
+fn conviction_multiplies_votes_and_lengthens_locks() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        assert_ok!(create_proposal(1, ProposalType::BlockReward, 100));
+        assert_ok!(create_proposal(1, ProposalType::BlockReward, 80));
+
+        // Sem convicção, 15 valem 1 voto. Com 3x valem 45, bloqueados por 4 períodos
+        assert_ok!(PowPallet::vote(
+            RuntimeOrigin::signed(2),
+            1,
+            false,
+            15,
+            Conviction::None
+        ));
+        assert_ok!(PowPallet::vote(
+            RuntimeOrigin::signed(2),
+            2,
+            false,
+            15,
+            Conviction::Locked3x
+        ));
+        assert_eq!(PowPallet::proposals(2).unwrap().votes_against, 45);
+
+        // O mesmo saldo sustenta os dois votos
+        assert_eq!(PowPallet::locked_stakes(2), Some((15, 18)));
+        assert_eq!(Balances::usable_balance(2), 5);
+
+        run_to_block(7);
+        System::assert_has_event(
+            Event::<Test>::ProposalExecuted(1, ProposalType::BlockReward, 100).into(),
+        );
+        System::assert_has_event(Event::<Test>::ProposalRejected(2, 5, 45).into());
+
+        run_to_block(17);
+        assert_noop!(
+            PowPallet::unlock_stake(RuntimeOrigin::signed(2)),
+            Error::<Test>::LockPeriodNotFinished
+        );
+
+        run_to_block(18);
+        assert_ok!(PowPallet::unlock_stake(RuntimeOrigin::signed(2)));
+        assert_eq!(Balances::usable_balance(2), 20);
+    });
+}
+
+#[test]
+fn delegated_stake_follows_expert_vote() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        for expert in [3, 4] {
+            Balances::make_free_balance_be(&expert, 100);
+            assert_ok!(PowPallet::verify_expert(
+                RuntimeOrigin::root(),
+                expert,
+                b"economia".to_vec()
+            ));
+        }
+        let delegate = |delegator, expert, conviction, balance| {
+            PowPallet::delegate(
+                RuntimeOrigin::signed(delegator),
+                ProposalType::BlockReward,
+                expert,
+                conviction,
+                balance,
+            )
+        };
+
+        // Só especialistas verificados recebem delegações
+        assert_noop!(
+            delegate(2, 1, Conviction::Locked1x, 10),
+            Error::<Test>::NotVerifiedExpert
+        );
+
+        // 2 delega a 3, que delega a 4: os votos de 2 seguem até 4
+        assert_ok!(delegate(2, 3, Conviction::Locked2x, 10));
+        System::assert_last_event(
+            Event::<Test>::Delegated(2, 3, ProposalType::BlockReward, 20).into(),
+        );
+        assert_ok!(delegate(3, 4, Conviction::Locked1x, 50));
+        assert_eq!(PowPallet::delegated_votes(3, ProposalType::BlockReward), 20);
+        assert_eq!(PowPallet::delegated_votes(4, ProposalType::BlockReward), 70);
+        assert_noop!(
+            delegate(4, 3, Conviction::Locked1x, 10),
+            Error::<Test>::DelegationCycle
+        );
+        assert_noop!(
+            delegate(3, 4, Conviction::Locked1x, 10),
+            Error::<Test>::AlreadyDelegating
+        );
+
+        // Quem delegou não vota em propostas do tipo delegado, mas vota nos outros tipos
+        assert_ok!(create_proposal(1, ProposalType::BlockReward, 100));
+        assert_ok!(create_proposal(1, ProposalType::HalvingPeriod, 20));
+        assert_noop!(
+            PowPallet::vote(RuntimeOrigin::signed(3), 1, true, 10, Conviction::Locked1x),
+            Error::<Test>::VotesDelegated
+        );
+        assert_ok!(PowPallet::vote(
+            RuntimeOrigin::signed(3),
+            2,
+            true,
+            30,
+            Conviction::Locked1x
+        ));
+        assert_eq!(PowPallet::proposals(2).unwrap().votes_for, 35);
+
+        // O voto de 4 leva os votos delegados, e a retirada de uma delegação os desconta
+        assert_ok!(PowPallet::vote(
+            RuntimeOrigin::signed(4),
+            1,
+            false,
+            10,
+            Conviction::Locked1x
+        ));
+        assert_eq!(PowPallet::proposals(1).unwrap().votes_against, 80);
+        assert_ok!(PowPallet::undelegate(
+            RuntimeOrigin::signed(2),
+            ProposalType::BlockReward
+        ));
+        assert_eq!(PowPallet::proposals(1).unwrap().votes_against, 60);
+        assert_eq!(PowPallet::votes(1, 4).unwrap().delegated_votes, 50);
+
+        // A participação conta o capital delegado, sem a convicção
+        assert_eq!(PowPallet::proposals(1).unwrap().turnout, 65);
+        assert_eq!(PowPallet::votes(1, 4).unwrap().delegated_capital, 50);
+        assert_noop!(
+            PowPallet::undelegate(RuntimeOrigin::signed(2), ProposalType::BlockReward),
+            Error::<Test>::NotDelegating
+        );
+
+        // Depois de votar, 2 só volta a delegar quando a proposta for apurada
+        assert_ok!(PowPallet::vote(
+            RuntimeOrigin::signed(2),
+            1,
+            true,
+            10,
+            Conviction::Locked1x
+        ));
+        assert_noop!(
+            delegate(2, 4, Conviction::Locked1x, 10),
+            Error::<Test>::VotedOnActiveProposal
+        );
+
+        run_to_block(7);
+        System::assert_has_event(Event::<Test>::ProposalRejected(1, 15, 60).into());
+        assert_ok!(delegate(2, 4, Conviction::Locked1x, 10));
+
+        // O saldo que sustenta uma delegação fica bloqueado até a retirada
+        run_to_block(9);
+        assert_noop!(
+            PowPallet::unlock_stake(RuntimeOrigin::signed(3)),
+            Error::<Test>::StakeDelegated
+        );
+    });
+}
+
+#[test]
+fn delegation_chains_are_bounded() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        for account in 1..=5 {
+            Balances::make_free_balance_be(&account, 100);
+            assert_ok!(PowPallet::verify_expert(
+                RuntimeOrigin::root(),
+                account,
+                b"economia".to_vec()
+            ));
+        }
+        let delegate = |delegator, expert| {
+            PowPallet::delegate(
+                RuntimeOrigin::signed(delegator),
+                ProposalType::BlockReward,
+                expert,
+                Conviction::Locked1x,
+                10,
+            )
+        };
+
+        // 2 delega a 3, que delega a 4, que delega a 5: uma cadeia de 3 delegações
+        assert_ok!(delegate(2, 3));
+        assert_ok!(delegate(3, 4));
+        assert_ok!(delegate(4, 5));
+        assert_eq!(PowPallet::delegation_depth(5, ProposalType::BlockReward), 3);
+
+        // Delegar a qualquer ponta da cadeia passaria de `MaxDelegationDepth`
+        assert_noop!(delegate(1, 2), Error::<Test>::DelegationTooDeep);
+        assert_noop!(delegate(5, 1), Error::<Test>::DelegationTooDeep);
+
+        // A cadeia encurta quando 4 retira a delegação
+        assert_ok!(PowPallet::undelegate(
+            RuntimeOrigin::signed(4),
+            ProposalType::BlockReward
+        ));
+        assert_eq!(PowPallet::delegation_depth(5, ProposalType::BlockReward), 0);
+        assert_ok!(delegate(1, 2));
+        assert_eq!(PowPallet::delegation_depth(4, ProposalType::BlockReward), 3);
+    });
+}
+
 #[test]
 fn max_supply_is_respected() {
     new_test_ext().execute_with(|| {
         // Define um suprimento total próximo do máximo
         let almost_max = MaxSupply::get() - 30;
-        PowPallet::TotalSupply::<Test>::put(almost_max);
-        
-        // Simula uma solução de PoW válida
-        let pre_hash = H256::zero();
-        let nonce = vec![1, 2, 3, 4];
-        let solution = BlakeTwo256::hash_of(&[&pre_hash.as_bytes()[..], &nonce[..]].concat());
-        
-        // Submete a solução
-        assert_ok!(PowPallet::submit_pow_solution(
-            RuntimeOrigin::signed(1),
-            nonce,
-            solution,
-            U256::from(1_000_000)
-        ));
-        
+        crate::TotalSupply::<Test>::put(almost_max);
+
+        // O autor do bloco recebe apenas o restante do suprimento máximo
+        next_block_authored_by(1);
+        next_block();
+
         // Verifica se a recompensa foi limitada ao restante do suprimento máximo
         assert_eq!(PowPallet::total_supply(), MaxSupply::get());
-        
-        // Tenta submeter outra solução
-        let nonce = vec![5, 6, 7, 8];
-        let solution = BlakeTwo256::hash_of(&[&pre_hash.as_bytes()[..], &nonce[..]].concat());
-        
-        // Submete a solução
-        assert_ok!(PowPallet::submit_pow_solution(
-            RuntimeOrigin::signed(1),
-            nonce,
-            solution,
-            U256::from(1_000_000)
-        ));
-        
+        assert_eq!(Balances::free_balance(1), 10 + 30);
+
+        // Minera outro bloco
+        next_block_authored_by(1);
+        next_block();
+
         // Verifica se o suprimento total não excedeu o máximo
         assert_eq!(PowPallet::total_supply(), MaxSupply::get());
+        assert_eq!(Balances::free_balance(1), 10 + 30);
+    });
+}
+
+// Recompensas esperadas bloco a bloco: os blocos `1..período` pagam a recompensa
+// inicial, e a cada período a recompensa cai pela metade, até atingir o teto
+fn expected_emission(block_reward: u64, blocks_per_halving: u64, max_supply: u64) -> Vec<u64> {
+    let mut rewards = Vec::new();
+    let mut supply = 0;
+    let mut number = 1;
+    while supply < max_supply {
+        let halvings = number / blocks_per_halving;
+        let reward = block_reward.checked_shr(halvings as u32).unwrap_or(0);
+        assert!(reward > 0, "o teto nunca seria atingido");
+        let minted = reward.min(max_supply - supply);
+        rewards.push(minted);
+        supply += minted;
+        number += 1;
+    }
+    rewards
+}
+
+#[test]
+fn emission_curve_ends_exactly_at_max_supply() {
+    // (recompensa, blocos por ano, suprimento máximo, halvings até o teto)
+    let schedules = [
+        (60, 10, 4_600, 2),
+        (60, 10, 2_940, 0),
+        (60, 10, 4_440, 1),
+        (7, 2, 100, 2),
+        (1_000, 1, 8_500, 4),
+    ];
+
+    for (block_reward, blocks_per_year, max_supply, halvings) in schedules {
+        new_test_ext_with_emission(block_reward, blocks_per_year, max_supply).execute_with(|| {
+            let blocks_per_halving = (blocks_per_year * HalvingYears::get()) as u64;
+            let mut minted = Vec::new();
+
+            next_block_authored_by(1);
+            while !PowPallet::fees_only() {
+                let supply = PowPallet::total_supply();
+                next_block_authored_by(1);
+                System::reset_events();
+
+                minted.push(PowPallet::total_supply() - supply);
+                assert!(PowPallet::total_supply() <= max_supply);
+            }
+
+            // Cada bloco paga a recompensa da sua era, e o último é limitado ao que falta
+            let expected = expected_emission(block_reward, blocks_per_halving, max_supply);
+            assert_eq!(minted, expected);
+            assert_eq!(PowPallet::current_block_reward(), block_reward >> halvings);
+            assert_eq!(
+                PowPallet::last_halving_block(),
+                halvings * blocks_per_halving
+            );
+
+            // O suprimento termina exatamente no máximo, sem moedas criadas fora da recompensa
+            assert_eq!(PowPallet::total_supply(), max_supply);
+            assert_eq!(PowPallet::remaining_emission(), 0);
+            assert_eq!(Balances::free_balance(1), 10 + max_supply);
+            assert_eq!(Balances::total_issuance(), 10 + 20 + max_supply);
+
+            // Depois do teto, nenhum bloco emite novas moedas
+            for _ in 0..10 {
+                next_block_authored_by(1);
+            }
+            assert_eq!(PowPallet::total_supply(), max_supply);
+            assert_eq!(Balances::free_balance(1), 10 + max_supply);
+        });
+    }
+}
+
+#[test]
+fn block_reward_changes_at_era_boundaries() {
+    // Halving a cada 50 blocos
+    new_test_ext_with_emission(60, 10, 4_600).execute_with(|| {
+        run_to_block(49);
+        assert_eq!(PowPallet::calculate_reward(), 60);
+
+        run_to_block(50);
+        assert_eq!(PowPallet::calculate_reward(), 30);
+        run_to_block(99);
+        assert_eq!(PowPallet::calculate_reward(), 30);
+
+        run_to_block(100);
+        assert_eq!(PowPallet::calculate_reward(), 15);
+
+        // Perto do teto, a recompensa é limitada ao que falta emitir
+        crate::TotalSupply::<Test>::put(4_600 - 7);
+        assert_eq!(PowPallet::calculate_reward(), 7);
+
+        crate::TotalSupply::<Test>::put(4_600);
+        assert_eq!(PowPallet::calculate_reward(), 0);
     });
 }
 
 #[test]
-fn difficulty_adjustment_works() {
+fn max_supply_reached_is_emitted_once() {
     new_test_ext().execute_with(|| {
-        // Avança para o bloco de ajuste de dificuldade
-        run_to_block(2016);
-        
-        // Verifica se a dificuldade foi ajustada
+        crate::TotalSupply::<Test>::put(MaxSupply::get() - 100);
+
+        // Dois blocos esgotam a emissão; os seguintes só recebem taxas
+        for _ in 0..6 {
+            next_block_authored_by(1);
+        }
+
+        let reached: Vec<_> = System::events()
+            .into_iter()
+            .filter(|record| {
+                matches!(record.event, RuntimeEvent::PowPallet(Event::MaxSupplyReached(..)))
+            })
+            .collect();
+        assert_eq!(reached.len(), 1);
+        System::assert_has_event(Event::<Test>::MaxSupplyReached(2, MaxSupply::get()).into());
+        assert!(PowPallet::fees_only());
+    });
+}
+
+#[test]
+fn fees_go_to_author_only_in_fees_only_mode() {
+    new_test_ext().execute_with(|| {
+        next_block_authored_by(1);
+        let pay_fee = || {
+            let fee = Balances::withdraw(
+                &2,
+                5,
+                WithdrawReasons::FEE,
+                ExistenceRequirement::KeepAlive,
+            )
+            .unwrap();
+            FeesToAuthor::<Test>::on_unbalanced(fee);
+        };
+
+        // Enquanto há emissão, as taxas são queimadas
+        pay_fee();
+        assert_eq!(Balances::free_balance(1), 10);
+        assert_eq!(Balances::total_issuance(), 10 + 20 - 5);
+
+        // No modo somente taxas, elas vão para o autor do bloco
+        crate::FeesOnly::<Test>::put(true);
+        pay_fee();
+        assert_eq!(Balances::free_balance(1), 10 + 5);
+        assert_eq!(Balances::total_issuance(), 10 + 20 - 5);
+    });
+}
+
+#[test]
+fn difficulty_is_stable_at_target_spacing() {
+    new_test_ext().execute_with(|| {
+        // Blocos exatamente no intervalo alvo não alteram a dificuldade
+        run_to_block(100);
+        assert_eq!(PowPallet::current_difficulty(), U256::from(1_000_000));
+
+        // A janela guarda no máximo `DifficultyWindow` tempos de solução
+        assert_eq!(
+            PowPallet::recent_blocks().len(),
+            DifficultyWindow::get() as usize + 1
+        );
+    });
+}
+
+#[test]
+fn difficulty_rises_when_blocks_are_fast() {
+    new_test_ext().execute_with(|| {
+        // Blocos na metade do intervalo alvo aumentam a dificuldade a cada bloco
+        run_to_block_with_spacing(30, TargetBlockTime::get() / 2);
+        assert!(PowPallet::current_difficulty() > U256::from(1_000_000));
+    });
+}
+
+#[test]
+fn difficulty_falls_when_blocks_are_slow() {
+    new_test_ext().execute_with(|| {
+        // Blocos no dobro do intervalo alvo reduzem a dificuldade
+        run_to_block_with_spacing(30, TargetBlockTime::get() * 2);
+        assert!(PowPallet::current_difficulty() < U256::from(1_000_000));
+    });
+}
+
+#[test]
+fn difficulty_change_is_clamped_per_block() {
+    new_test_ext().execute_with(|| {
+        // Com tempos de solução quase nulos, o LWMA pediria um salto enorme,
+        // mas a mudança por bloco é limitada por `DifficultyClampFactor`
+        run_to_block_with_spacing(3, 1);
         assert_eq!(
             PowPallet::current_difficulty(),
-            U256::from(1_000_000).saturating_mul(U256::from(105)) / U256::from(100)
+            U256::from(1_000_000) * U256::from(DifficultyClampFactor::get())
+        );
+    });
+}
+
+#[test]
+fn total_difficulty_accumulates() {
+    new_test_ext().execute_with(|| {
+        // Cada bloco finalizado soma a dificuldade em que foi minerado ao trabalho total
+        run_to_block(10);
+        assert_eq!(
+            PowPallet::total_difficulty(),
+            U256::from(1_000_000) * U256::from(9)
+        );
+
+        // O último bloco da janela carrega o mesmo total
+        let blocks = PowPallet::recent_blocks();
+        assert_eq!(
+            blocks.last().unwrap().total_difficulty,
+            PowPallet::total_difficulty()
         );
     });
 }
@@ -102,40 +815,536 @@ fn reward_payment_works() {
     new_test_ext().execute_with(|| {
         // Verifica o saldo inicial do minerador
         assert_eq!(Balances::free_balance(1), 10);
-        
-        // Simula uma solução de PoW válida
-        let pre_hash = H256::zero();
-        let nonce = vec![1, 2, 3, 4];
-        let solution = BlakeTwo256::hash_of(&[&pre_hash.as_bytes()[..], &nonce[..]].concat());
-        
-        // Submete a solução
-        assert_ok!(PowPallet::submit_pow_solution(
-            RuntimeOrigin::signed(1),
-            nonce,
-            solution,
-            U256::from(1_000_000)
-        ));
-        
-        // Verifica se a recompensa foi paga
+
+        // O minerador inclui sua conta no digest pré-runtime do bloco
+        next_block_authored_by(1);
+        next_block();
+
+        // Verifica se a recompensa foi paga ao finalizar o bloco
         assert_eq!(Balances::free_balance(1), 10 + 60);
-        
+        System::assert_has_event(Event::<Test>::RewardPaid(1, 60, 1).into());
+
         // Verifica se o suprimento total foi atualizado
         assert_eq!(PowPallet::total_supply(), 60);
     });
 }
 
 #[test]
-fn invalid_pow_solution_is_rejected() {
+fn reward_goes_to_new_accounts() {
+    new_test_ext().execute_with(|| {
+        // Um minerador sem saldo também recebe a recompensa
+        next_block_authored_by(3);
+        next_block();
+
+        assert_eq!(Balances::free_balance(3), 60);
+        assert_eq!(Balances::total_issuance(), 10 + 20 + 60);
+    });
+}
+
+#[test]
+fn block_without_author_is_not_rewarded() {
+    new_test_ext().execute_with(|| {
+        // Blocos sem digest de autor não emitem recompensa
+        run_to_block(5);
+        assert_eq!(PowPallet::total_supply(), 0);
+
+        // Um digest com outro engine ID ou que não decodifica uma conta é ignorado
+        next_block_with_digest(Digest {
+            logs: vec![DigestItem::PreRuntime(*b"aura", 1u64.encode())],
+        });
+        next_block_with_digest(Digest {
+            logs: vec![DigestItem::PreRuntime(POW_ENGINE_ID, vec![1, 2, 3])],
+        });
+        next_block();
+
+        assert_eq!(PowPallet::total_supply(), 0);
+        assert_eq!(Balances::free_balance(1), 10);
+    });
+}
+
+#[test]
+fn block_author_is_recorded() {
     new_test_ext().execute_with(|| {
-        // Tenta submeter uma solução com dificuldade muito baixa
+        // O autor é decodificado do digest pré-runtime ao iniciar o bloco
+        next_block_authored_by(2);
+        assert_eq!(PowPallet::author(), Some(2));
+
+        // E fica registrado nas informações do bloco
+        next_block();
+        assert_eq!(PowPallet::author(), None);
+        let blocks = PowPallet::recent_blocks();
+        assert_eq!(blocks[0].author, Some(2));
+        assert_eq!(blocks[0].number, 1);
+    });
+}
+
+// Qualquer conta pode enviar um tio; nos testes é sempre a conta 1
+fn include_uncle(uncle: HeaderFor<Test>) -> DispatchResult {
+    PowPallet::include_uncle(RuntimeOrigin::signed(1), uncle)
+}
+
+#[test]
+fn uncle_miner_and_includer_are_rewarded() {
+    new_test_ext().execute_with(|| {
+        // Dificuldade mínima para que os tios sejam minerados na hora
+        crate::CurrentDifficulty::<Test>::put(U256::one());
+        run_to_block(4);
+        next_block_authored_by(2);
+
+        // Um tio dois blocos abaixo rende 6/8 da recompensa ao seu minerador
+        let uncle = mine_uncle(3, 3);
+        let uncle_hash = uncle.hash();
+        assert_ok!(PowPallet::include_uncle(RuntimeOrigin::signed(1), uncle));
+        assert_eq!(Balances::free_balance(3), 45);
+        System::assert_has_event(Event::<Test>::UncleIncluded(uncle_hash, 3, 45).into());
+
+        // E o autor do bloco que o incluiu recebe 1/32, não quem enviou a transação
+        assert_eq!(Balances::free_balance(2), 20 + 1);
+        assert_eq!(Balances::free_balance(1), 10);
+        assert_eq!(PowPallet::total_supply(), 45 + 1);
+    });
+}
+
+#[test]
+fn invalid_uncles_are_rejected() {
+    new_test_ext().execute_with(|| {
+        crate::CurrentDifficulty::<Test>::put(U256::one());
+        run_to_block(9);
+        next_block_authored_by(2);
+        let author = Digest {
+            logs: vec![DigestItem::PreRuntime(POW_ENGINE_ID, 3u64.encode())],
+        };
+
+        // O tio precisa estar abaixo do bloco atual e no máximo `MaxUncleDepth` abaixo
         assert_noop!(
-            PowPallet::submit_pow_solution(
-                RuntimeOrigin::signed(1),
-                vec![1, 2, 3, 4],
-                H256::zero(),
-                U256::from(100) // Dificuldade muito baixa
-            ),
-            Error::<Test>::DifficultyTooLow
+            include_uncle(mine_uncle(10, 3)),
+            Error::<Test>::UncleNotStale
+        );
+        assert_noop!(include_uncle(mine_uncle(3, 3)), Error::<Test>::UncleTooOld);
+
+        // O pai do tio precisa fazer parte da cadeia
+        let orphan = seal_header(
+            uncle_header(8, H256::repeat_byte(7), author.clone()),
+            U256::one(),
+        );
+        assert_noop!(include_uncle(orphan), Error::<Test>::UncleParentNotInChain);
+
+        // Sem digest de autor não há a quem pagar
+        let anonymous = seal_header(
+            uncle_header(8, H256::from_low_u64_be(7), Digest::default()),
+            U256::one(),
+        );
+        assert_noop!(include_uncle(anonymous), Error::<Test>::UncleAuthorNotFound);
+
+        // Cabeçalhos sem selo ou selados com outra dificuldade não são aceitos
+        let unsealed = uncle_header(8, H256::from_low_u64_be(7), author.clone());
+        assert_noop!(
+            include_uncle(unsealed.clone()),
+            Error::<Test>::InvalidUncleSeal
+        );
+        assert_noop!(
+            include_uncle(seal_header(unsealed, U256::from(2))),
+            Error::<Test>::InvalidUncleSeal
+        );
+    });
+}
+
+#[test]
+fn seals_are_versioned_and_fixed_width() {
+    let seal = CivicSeal::new(
+        U256::from(3),
+        [7; NONCE_SIZE],
+        H256::repeat_byte(1),
+        H256::repeat_byte(2),
+    );
+    let raw = seal.encode();
+
+    // O selo tem sempre o mesmo tamanho e começa pela versão
+    assert_eq!(raw.len(), CivicSeal::ENCODED_SIZE);
+    assert_eq!(raw[0], SEAL_VERSION);
+    assert_eq!(CivicSeal::decode_raw(&raw), Some(seal.clone()));
+
+    // Bytes a mais, como um nonce gigante, são rejeitados antes de decodificar
+    let mut oversize = raw.clone();
+    oversize.extend_from_slice(&[0; 1_000]);
+    assert_eq!(CivicSeal::decode_raw(&oversize), None);
+    assert_eq!(CivicSeal::decode_raw(&raw[..raw.len() - 1]), None);
+
+    // Versões desconhecidas também
+    let mut unknown = raw;
+    unknown[0] = SEAL_VERSION + 1;
+    assert_eq!(CivicSeal::decode_raw(&unknown), None);
+
+    new_test_ext().execute_with(|| {
+        crate::CurrentDifficulty::<Test>::put(U256::one());
+        run_to_block(9);
+        next_block_authored_by(2);
+
+        // Um selo que aponta para outra sequência PoH não vale para o tio
+        let mut uncle = mine_uncle(8, 3);
+        let mut seal = match uncle.digest_mut().pop() {
+            Some(DigestItem::Seal(_, raw)) => CivicSeal::decode_raw(&raw).unwrap(),
+            _ => unreachable!(),
+        };
+        seal.poh = H256::repeat_byte(1);
+        uncle
+            .digest_mut()
+            .push(DigestItem::Seal(POW_ENGINE_ID, seal.encode()));
+        assert_noop!(include_uncle(uncle), Error::<Test>::InvalidUncleSeal);
+    });
+}
+
+#[test]
+fn pow_params_switch_at_scheduled_height() {
+    let params = PowParams {
+        personalization: b"civicchain-2".to_vec(),
+    };
+
+    // Antes da primeira ativação vale o Yespower-R16 puro
+    let mut schedule = PowSchedule::default();
+    schedule.insert(20, params.clone());
+    schedule.insert(10, PowParams::default());
+    schedule.insert(10, params.clone());
+    assert_eq!(schedule.0.len(), 2);
+    assert_eq!(schedule.params_at(9), PowParams::default());
+    assert_eq!(schedule.params_at(10), params);
+    assert_eq!(schedule.params_at(25), params);
+
+    // O mesmo nonce gera outro hash com outra personalização
+    let pre_hash = H256::repeat_byte(3);
+    assert_ne!(
+        compute_work(&PowParams::default(), pre_hash.as_bytes(), &[0; NONCE_SIZE]),
+        compute_work(&params, pre_hash.as_bytes(), &[0; NONCE_SIZE])
+    );
+
+    new_test_ext().execute_with(|| {
+        crate::CurrentDifficulty::<Test>::put(U256::one());
+        run_to_block(4);
+
+        // Só a raiz agenda, com pelo menos `MinPowParamsDelay` blocos de antecedência
+        assert_noop!(
+            PowPallet::schedule_pow_params(RuntimeOrigin::signed(1), 15, params.clone()),
+            sp_runtime::DispatchError::BadOrigin
         );
+        assert_noop!(
+            PowPallet::schedule_pow_params(RuntimeOrigin::root(), 13, params.clone()),
+            Error::<Test>::PowParamsActivationTooSoon
+        );
+        let oversize = PowParams {
+            personalization: vec![0; MAX_PERSONALIZATION_LEN + 1],
+        };
+        assert_noop!(
+            PowPallet::schedule_pow_params(RuntimeOrigin::root(), 15, oversize),
+            Error::<Test>::InvalidPowParams
+        );
+
+        assert_ok!(PowPallet::schedule_pow_params(
+            RuntimeOrigin::root(),
+            15,
+            params.clone()
+        ));
+        System::assert_last_event(Event::<Test>::PowParamsScheduled(15, params.clone()).into());
+        assert_eq!(PowPallet::pow_params(), PowParams::default());
+
+        // O bloco 14 já informa os parâmetros do bloco seguinte
+        run_to_block(14);
+        assert_eq!(PowPallet::pow_params(), params);
+
+        // Tios são verificados com os parâmetros da sua altura
+        run_to_block(17);
+        next_block_authored_by(2);
+        let author = Digest {
+            logs: vec![DigestItem::PreRuntime(POW_ENGINE_ID, 3u64.encode())],
+        };
+        let outdated = seal_header(
+            uncle_header(16, H256::from_low_u64_be(15), author),
+            PowPallet::difficulty(),
+        );
+        assert_noop!(include_uncle(outdated), Error::<Test>::InvalidUncleSeal);
+        assert_ok!(include_uncle(mine_uncle(16, 3)));
+    });
+}
+
+#[test]
+fn uncles_are_limited_and_not_included_twice() {
+    new_test_ext().execute_with(|| {
+        crate::CurrentDifficulty::<Test>::put(U256::one());
+        run_to_block(9);
+        next_block_authored_by(2);
+
+        // Cada tio só pode ser incluído uma vez
+        assert_ok!(include_uncle(mine_uncle(8, 3)));
+        assert_noop!(
+            include_uncle(mine_uncle(8, 3)),
+            Error::<Test>::UncleAlreadyIncluded
+        );
+
+        // No máximo `MaxUncles` tios por bloco
+        assert_ok!(include_uncle(mine_uncle(8, 4)));
+        assert_noop!(
+            include_uncle(mine_uncle(9, 3)),
+            Error::<Test>::TooManyUncles
+        );
+
+        // No bloco seguinte há espaço de novo, mas a duplicata continua barrada
+        next_block_authored_by(2);
+        assert_noop!(
+            include_uncle(mine_uncle(8, 3)),
+            Error::<Test>::UncleAlreadyIncluded
+        );
+        assert_ok!(include_uncle(mine_uncle(9, 3)));
+
+        // Tios que já não podem ser incluídos são esquecidos
+        run_to_block(16);
+        assert_eq!(PowPallet::uncles().len(), 0);
+    });
+}
+
+#[test]
+fn poh_sequence_is_recorded_and_deposited() {
+    new_test_ext().execute_with(|| {
+        run_to_block(3);
+
+        // A sequência começa no hash do bloco pai
+        let parent_hash = System::parent_hash();
+        let poh = PohDigest::compute(parent_hash, 100);
+        assert_ok!(PowPallet::set_poh(RuntimeOrigin::none(), poh));
+
+        assert_eq!(PowPallet::last_poh_hash(), poh.hash);
+        assert_eq!(PowPallet::poh_counter(), 100);
+        assert!(System::digest().logs.contains(&poh.to_digest_item()));
+
+        // E fica registrada nas informações do bloco
+        next_block();
+        assert_eq!(
+            PowPallet::recent_blocks().last().unwrap().poh_hash,
+            poh.hash
+        );
+
+        // O contador acumula os ticks de todos os blocos
+        let poh = PohDigest::compute(System::parent_hash(), 50);
+        assert_ok!(PowPallet::set_poh(RuntimeOrigin::none(), poh));
+        assert_eq!(PowPallet::poh_counter(), 150);
+    });
+}
+
+#[test]
+fn invalid_poh_sequence_is_rejected() {
+    new_test_ext().execute_with(|| {
+        run_to_block(3);
+        let parent_hash = System::parent_hash();
+
+        // Sequência iniciada em outro hash
+        let poh = PohDigest::compute(H256::repeat_byte(1), 10);
+        assert_noop!(
+            PowPallet::set_poh(RuntimeOrigin::none(), poh),
+            Error::<Test>::PohVerificationFailed
+        );
+
+        // Contagem de ticks que não corresponde ao hash
+        let mut poh = PohDigest::compute(parent_hash, 10);
+        poh.ticks = 11;
+        assert_noop!(
+            PowPallet::set_poh(RuntimeOrigin::none(), poh),
+            Error::<Test>::PohVerificationFailed
+        );
+
+        // Mais ticks que `MaxPohTicks`
+        let poh = PohDigest::compute(parent_hash, MaxPohTicks::get() + 1);
+        assert_noop!(
+            PowPallet::set_poh(RuntimeOrigin::none(), poh),
+            Error::<Test>::PohVerificationFailed
+        );
+
+        // Só o produtor do bloco pode definir a sequência, como inherent
+        let poh = PohDigest::compute(parent_hash, 10);
+        assert_noop!(
+            PowPallet::set_poh(RuntimeOrigin::signed(1), poh),
+            sp_runtime::DispatchError::BadOrigin
+        );
+    });
+}
+
+#[test]
+fn poh_inherent_is_required() {
+    new_test_ext().execute_with(|| {
+        let poh = PohDigest::compute(H256::zero(), 10);
+        let mut data = InherentData::new();
+        assert_eq!(PowPallet::create_inherent(&data), None);
+        assert!(PowPallet::is_inherent_required(&data).unwrap().is_some());
+
+        // O inherent é criado a partir da sequência fornecida pelo nó
+        data.put_data(poh::INHERENT_IDENTIFIER, &poh).unwrap();
+        let call = PowPallet::create_inherent(&data).unwrap();
+        assert_eq!(call, Call::set_poh { poh });
+        assert!(PowPallet::is_inherent(&call));
+        assert!(PowPallet::check_inherent(&call, &data).is_ok());
+
+        // Sequências longas demais são recusadas antes de serem calculadas
+        let mut poh = poh;
+        poh.ticks = MaxPohTicks::get() + 1;
+        assert!(PowPallet::check_inherent(&Call::set_poh { poh }, &data).is_err());
+    });
+}
+
+#[test]
+fn block_hooks_are_weighed() {
+    new_test_ext().execute_with(|| {
+        run_to_block(2);
+        let idle = PowPallet::on_initialize(3);
+        assert!(idle.ref_time() > 0);
+
+        // Cada proposta ativa aumenta o peso do bloco
+        assert_ok!(create_proposal(1, ProposalType::BlockReward, 100));
+        assert_ok!(create_proposal(1, ProposalType::DifficultyAdjustment, 100));
+        assert!(PowPallet::on_initialize(3).ref_time() > idle.ref_time());
+    });
+}
+
+#[test]
+fn poh_weight_scales_with_ticks() {
+    new_test_ext().execute_with(|| {
+        let weight = |ticks| {
+            let poh = PohDigest::compute(H256::zero(), ticks);
+            Call::<Test>::set_poh { poh }.get_dispatch_info().weight
+        };
+
+        // Cada tick custa um hash a mais
+        assert!(weight(100).ref_time() > weight(10).ref_time());
+
+        // O peso para em `MaxPohTicks`, acima disso a chamada é recusada
+        let mut poh = PohDigest::compute(H256::zero(), 10);
+        poh.ticks = u64::MAX;
+        assert_eq!(
+            Call::<Test>::set_poh { poh }.get_dispatch_info().weight,
+            weight(MaxPohTicks::get())
+        );
+    });
+}
+
+#[test]
+fn poh_header_can_be_verified_alone() {
+    // Um cliente leve confere a sequência só com o cabeçalho
+    let parent_hash = H256::repeat_byte(3);
+    let poh = PohDigest::compute(parent_hash, 20);
+    let header = uncle_header(
+        5,
+        parent_hash,
+        Digest {
+            logs: vec![poh.to_digest_item()],
+        },
+    );
+    assert!(poh::verify_header(&header, 20));
+    assert_eq!(PohDigest::find(header.digest()), Some(poh));
+
+    // Mas não aceita mais ticks que o limite, outro pai ou a falta do digest
+    assert!(!poh::verify_header(&header, 19));
+    let other_parent = uncle_header(5, H256::repeat_byte(4), header.digest().clone());
+    assert!(!poh::verify_header(&other_parent, 20));
+    assert!(!poh::verify_header(
+        &uncle_header(5, parent_hash, Digest::default()),
+        20
+    ));
+
+    // Cada tick é um SHA-256 do anterior
+    assert_eq!(
+        PohDigest::compute(parent_hash, 2).hash,
+        poh::tick(&poh::tick(&parent_hash))
+    );
+
+    // O runtime calcula o mesmo tick pelo host
+    assert_eq!(poh::native_tick(&parent_hash), poh::tick(&parent_hash));
+    assert!(poh.verify_with(parent_hash, 20, poh::native_tick));
+}
+
+#[test]
+fn mmr_commits_to_every_previous_block() {
+    new_test_ext().execute_with(|| {
+        run_to_block(4);
+
+        // Folhas dos blocos 0 a 3; o bloco gênese não tem dificuldade
+        let difficulty = U256::from(1_000_000);
+        let leaf = |n: u64, difficulty| MmrNode::leaf(H256::from_low_u64_be(n), difficulty);
+        let expected = MmrNode::merge(
+            &MmrNode::merge(&leaf(0, U256::zero()), &leaf(1, difficulty)),
+            &MmrNode::merge(&leaf(2, difficulty), &leaf(3, difficulty)),
+        );
+
+        let commitment = PowPallet::mmr().commitment().unwrap();
+        assert_eq!(commitment.leaf_count, 4);
+        assert_eq!(commitment.root, expected);
+
+        // A raiz carrega a dificuldade acumulada da cadeia até o bloco pai
+        assert_eq!(commitment.root.difficulty, PowPallet::total_difficulty());
+
+        // E vai para o cabeçalho do bloco
+        assert_eq!(MmrCommitment::find(&System::digest()), Some(commitment));
+    });
+}
+
+#[test]
+fn difficulty_follows_current_difficulty() {
+    new_test_ext().execute_with(|| {
+        // A dificuldade exposta pela runtime API vem do armazenamento
+        assert_eq!(PowPallet::difficulty(), U256::from(1_000_000));
+
+        crate::CurrentDifficulty::<Test>::put(U256::from(42));
+        assert_eq!(PowPallet::difficulty(), U256::from(42));
+
+        // Dificuldade zero aceitaria qualquer hash, então nunca é exposta
+        crate::CurrentDifficulty::<Test>::put(U256::zero());
+        assert_eq!(PowPallet::difficulty(), U256::one());
+    });
+}
+
+#[test]
+fn median_time_past_is_median_of_recent_blocks() {
+    new_test_ext().execute_with(|| {
+        // Sem blocos registrados não há mediana
+        assert_eq!(PowPallet::median_time_past(), None);
+
+        // Após 20 blocos no intervalo alvo, a mediana é o 6º dos últimos 11 timestamps
+        run_to_block(21);
+        let spacing = TargetBlockTime::get();
+        assert_eq!(PowPallet::median_time_past(), Some(15 * spacing));
+    });
+}
+
+#[test]
+#[should_panic(expected = "Timestamp must be greater than the median time past")]
+fn backdated_timestamp_is_rejected() {
+    new_test_ext().execute_with(|| {
+        run_to_block(21);
+
+        // Um minerador tenta datar o bloco na mediana dos blocos recentes
+        next_block();
+        let median = PowPallet::median_time_past().unwrap();
+        Timestamp::set_timestamp(median);
+    });
+}
+
+#[test]
+fn time_warp_attack_does_not_lower_difficulty() {
+    new_test_ext().execute_with(|| {
+        // Cadeia honesta no intervalo alvo
+        run_to_block(21);
+        let honest_difficulty = PowPallet::current_difficulty();
+
+        // O atacante data cada bloco no mínimo permitido (mediana + 1),
+        // tentando acumular tempo para um salto posterior
+        for _ in 0..30 {
+            next_block();
+            let median = PowPallet::median_time_past().unwrap();
+            Timestamp::set_timestamp(median + 1);
+        }
+
+        // Em seguida, salta o timestamp muito à frente para simular blocos lentos
+        next_block();
+        Timestamp::set_timestamp(Timestamp::now() + 100 * TargetBlockTime::get());
+        next_block();
+
+        // Timestamps atrasados contam como tempo de solução zero e o salto é limitado,
+        // então a dificuldade não cai abaixo da dificuldade honesta
+        assert!(PowPallet::current_difficulty() >= honest_difficulty);
     });
 }
@@ -0,0 +1,334 @@
+//! FlyClient proofs: check a chain's work from a handful of sampled headers.
+//!
+//! Every header commits to an MMR over all blocks before it (see [`crate::mmr`]).
+//! To prove a chain, a full node sends the head header, an inclusion proof for
+//! the genesis block and `security_param` sampled headers, each with an
+//! inclusion proof against the head's commitment.
+//!
+//! Sample `i` is the block whose slice of the cumulative difficulty contains
+//! [`sample_point`]`(head_hash, i, total_difficulty)`, so blocks are drawn by
+//! work and the head alone fixes which ones. The prover cannot pick them, and a
+//! chain claiming more work than it holds fails a sample with high probability.
+//!
+//! [`verify`] checks the Yespower seal of every header and needs nothing but the
+//! genesis hash. Seals are checked with the work function parameters the proof
+//! says were in force. Those come from the prover, but every parameter set
+//! costs the same work per hash, so they cannot make a chain look heavier.
+//! Like the rest of the crate, it never calls into the host, so it runs in
+//! wasm wallets.
+//!
+//! MMR nodes are addressed by the last leaf they cover and their height: node
+//! `(i, h)` is the perfect subtree of height `h` whose rightmost leaf is `i`.
+//! Provers look nodes up this way through [`prove`] and [`find_leaf`].
+
+use crate::{
+    mmr::{bag_peaks, MmrCommitment, MmrNode},
+    yespower::{CivicSeal, PowSchedule},
+};
+use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::{hashing::blake2_256, H256, U256};
+use sp_runtime::{
+    traits::{Header as HeaderT, UniqueSaturatedInto},
+    DigestItem, RuntimeDebug,
+};
+use sp_std::prelude::*;
+
+/// Most samples a proof may be asked for.
+pub const MAX_SECURITY_PARAM: u32 = 256;
+
+/// Perfect subtree of an MMR, covering leaves `start..start + 2^height`.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct Mountain {
+    /// First leaf covered.
+    pub start: u64,
+    /// Height of the subtree.
+    pub height: u32,
+}
+
+impl Mountain {
+    /// Number of leaves covered.
+    pub fn width(&self) -> u64 {
+        1 << self.height
+    }
+
+    /// Rightmost leaf covered.
+    pub fn last(&self) -> u64 {
+        self.start + self.width() - 1
+    }
+
+    /// Whether `leaf` is covered.
+    pub fn contains(&self, leaf: u64) -> bool {
+        leaf >= self.start && leaf - self.start < self.width()
+    }
+}
+
+/// Mountains of an MMR with `leaf_count` leaves, tallest (leftmost) first.
+pub fn mountains(leaf_count: u64) -> Vec<Mountain> {
+    let mut start = 0;
+    (0..u64::BITS)
+        .rev()
+        .filter(|height| (leaf_count >> height) & 1 == 1)
+        .map(|height| {
+            let mountain = Mountain { start, height };
+            start += mountain.width();
+            mountain
+        })
+        .collect()
+}
+
+/// Proof that a leaf belongs to the range an [`MmrCommitment`] commits to.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct MmrProof {
+    /// Index of the leaf, which is the number of its block.
+    pub leaf_index: u64,
+    /// Siblings on the path from the leaf up to its peak, lowest first.
+    pub siblings: Vec<MmrNode>,
+    /// Peaks of the range other than the leaf's own, leftmost first.
+    pub peaks: Vec<MmrNode>,
+}
+
+impl MmrProof {
+    /// Check that `leaf` is leaf `leaf_index` of the range committed to by
+    /// `commitment`. Returns the total difficulty of the leaves before it.
+    pub fn verify(&self, leaf: MmrNode, commitment: &MmrCommitment) -> Option<U256> {
+        let mountains = mountains(commitment.leaf_count);
+        let position = mountains
+            .iter()
+            .position(|mountain| mountain.contains(self.leaf_index))?;
+        let mountain = mountains[position];
+        if self.siblings.len() != mountain.height as usize
+            || self.peaks.len() + 1 != mountains.len()
+        {
+            return None;
+        }
+
+        let offset = self.leaf_index - mountain.start;
+        let mut node = leaf;
+        let mut before = U256::zero();
+        for (level, sibling) in self.siblings.iter().enumerate() {
+            if (offset >> level) & 1 == 0 {
+                node = MmrNode::merge(&node, sibling);
+            } else {
+                before = before.saturating_add(sibling.difficulty);
+                node = MmrNode::merge(sibling, &node);
+            }
+        }
+
+        let mut peaks = self.peaks.clone();
+        for peak in &peaks[..position] {
+            before = before.saturating_add(peak.difficulty);
+        }
+        peaks.insert(position, node);
+
+        (bag_peaks(&peaks)? == commitment.root).then_some(before)
+    }
+}
+
+/// Build the proof for `leaf_index` in an MMR of `leaf_count` leaves, looking
+/// nodes up by last leaf and height. `None` if a node is missing.
+pub fn prove<F>(leaf_index: u64, leaf_count: u64, node: F) -> Option<MmrProof>
+where
+    F: Fn(u64, u32) -> Option<MmrNode>,
+{
+    let mountains = mountains(leaf_count);
+    let position = mountains
+        .iter()
+        .position(|mountain| mountain.contains(leaf_index))?;
+    let mountain = mountains[position];
+    let offset = leaf_index - mountain.start;
+
+    let siblings = (0..mountain.height)
+        .map(|level| {
+            let width = 1u64 << level;
+            // Rightmost leaf of the subtree at `level` holding the leaf
+            let last = mountain.start + (offset >> level << level) + width - 1;
+            if (offset >> level) & 1 == 0 {
+                node(last + width, level)
+            } else {
+                node(last - width, level)
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let peaks = mountains
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != position)
+        .map(|(_, mountain)| node(mountain.last(), mountain.height))
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(MmrProof {
+        leaf_index,
+        siblings,
+        peaks,
+    })
+}
+
+/// Leaf whose slice of the cumulative difficulty contains `point`, in an MMR of
+/// `leaf_count` leaves. `None` if `point` is past the total or a node is missing.
+pub fn find_leaf<F>(mut point: U256, leaf_count: u64, node: F) -> Option<u64>
+where
+    F: Fn(u64, u32) -> Option<MmrNode>,
+{
+    for mountain in mountains(leaf_count) {
+        let peak = node(mountain.last(), mountain.height)?;
+        if point >= peak.difficulty {
+            point -= peak.difficulty;
+            continue;
+        }
+
+        let mut last = mountain.last();
+        for height in (0..mountain.height).rev() {
+            let left = node(last - (1 << height), height)?;
+            if point < left.difficulty {
+                last -= 1 << height;
+            } else {
+                point -= left.difficulty;
+            }
+        }
+        return Some(last);
+    }
+
+    None
+}
+
+/// Point of the cumulative difficulty that sample `index` of a proof for
+/// `head_hash` must cover. `None` if the chain has no work yet.
+pub fn sample_point(head_hash: &H256, index: u32, total_difficulty: U256) -> Option<U256> {
+    let seed = blake2_256(&(head_hash, index).encode());
+    U256::from(&seed[..]).checked_rem(total_difficulty)
+}
+
+/// FlyClient proof for a chain head.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct FlyClientProof<Header> {
+    /// Head of the chain, sealed.
+    pub head: Header,
+    /// Inclusion of the genesis block, leaf 0.
+    pub genesis: MmrProof,
+    /// Sampled headers with their inclusion proofs, in sample order.
+    pub samples: Vec<(Header, MmrProof)>,
+    /// Work function parameters scheduled as of the head.
+    pub schedule: PowSchedule,
+}
+
+/// Chain head accepted by [`verify`].
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct VerifiedHead {
+    /// Hash of the head.
+    pub hash: H256,
+    /// Number of the head.
+    pub number: u64,
+    /// Total difficulty of the chain, head included.
+    pub total_difficulty: U256,
+}
+
+/// Reasons a FlyClient proof is rejected.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum VerificationError {
+    /// More samples were asked for than [`MAX_SECURITY_PARAM`].
+    SecurityParamTooHigh,
+    /// A header has a missing or invalid seal.
+    InvalidSeal,
+    /// The head carries no MMR commitment over the blocks before it.
+    MissingCommitment,
+    /// The chain does not start at the expected genesis block.
+    GenesisMismatch,
+    /// The proof does not carry one sample per unit of security parameter.
+    WrongSampleCount,
+    /// A sampled header is not part of the head's MMR.
+    InvalidInclusionProof,
+    /// A sampled header is not the block the sample must cover.
+    WrongSample,
+}
+
+/// Hash of `header`, computed without calling into the host.
+pub fn header_hash<Header: Encode>(header: &Header) -> H256 {
+    H256(blake2_256(&header.encode()))
+}
+
+/// Check the Yespower seal of `header`, under the parameters `schedule` sets
+/// for its height, and the PoH sequence it refers to. Returns the difficulty
+/// the header was mined at.
+pub fn verify_seal<Header>(
+    header: &Header,
+    schedule: &PowSchedule,
+) -> Result<U256, VerificationError>
+where
+    Header: HeaderT<Hash = H256>,
+{
+    let mut header = header.clone();
+    let seal = match header.digest_mut().pop() {
+        Some(DigestItem::Seal(id, seal)) if id == POW_ENGINE_ID => {
+            CivicSeal::decode_raw(&seal).ok_or(VerificationError::InvalidSeal)?
+        }
+        _ => return Err(VerificationError::InvalidSeal),
+    };
+
+    let params = schedule.params_at((*header.number()).unique_saturated_into());
+    let pre_hash = header_hash(&header);
+    if !seal.follows_poh(header.digest()) || !seal.verify(&params, pre_hash.as_bytes()) {
+        return Err(VerificationError::InvalidSeal);
+    }
+    Ok(seal.difficulty)
+}
+
+/// Check `proof` for a chain starting at `genesis_hash`, drawing
+/// `security_param` samples.
+pub fn verify<Header>(
+    proof: &FlyClientProof<Header>,
+    genesis_hash: H256,
+    security_param: u32,
+) -> Result<VerifiedHead, VerificationError>
+where
+    Header: HeaderT<Hash = H256>,
+{
+    if security_param > MAX_SECURITY_PARAM {
+        return Err(VerificationError::SecurityParamTooHigh);
+    }
+
+    let head_difficulty = verify_seal(&proof.head, &proof.schedule)?;
+    let head_hash = header_hash(&proof.head);
+    let number: u64 = (*proof.head.number()).unique_saturated_into();
+    let commitment = MmrCommitment::find(proof.head.digest())
+        .filter(|commitment| commitment.leaf_count == number)
+        .ok_or(VerificationError::MissingCommitment)?;
+
+    let genesis = MmrNode::leaf(genesis_hash, U256::zero());
+    if proof.genesis.leaf_index != 0 || proof.genesis.verify(genesis, &commitment).is_none() {
+        return Err(VerificationError::GenesisMismatch);
+    }
+
+    // A chain with no work past genesis has nothing to sample
+    let total = commitment.root.difficulty;
+    let samples = if total.is_zero() { 0 } else { security_param };
+    if proof.samples.len() != samples as usize {
+        return Err(VerificationError::WrongSampleCount);
+    }
+
+    for (index, (header, inclusion)) in proof.samples.iter().enumerate() {
+        let difficulty = verify_seal(header, &proof.schedule)?;
+        let leaf = MmrNode::leaf(header_hash(header), difficulty);
+        let leaf_index: u64 = (*header.number()).unique_saturated_into();
+        if inclusion.leaf_index != leaf_index {
+            return Err(VerificationError::InvalidInclusionProof);
+        }
+        let before = inclusion
+            .verify(leaf, &commitment)
+            .ok_or(VerificationError::InvalidInclusionProof)?;
+
+        let point =
+            sample_point(&head_hash, index as u32, total).ok_or(VerificationError::WrongSample)?;
+        if point < before || point >= before.saturating_add(difficulty) {
+            return Err(VerificationError::WrongSample);
+        }
+    }
+
+    Ok(VerifiedHead {
+        hash: head_hash,
+        number,
+        total_difficulty: total.saturating_add(head_difficulty),
+    })
+}
@@ -0,0 +1,20 @@
+//! # CivicChain Primitives
+//!
+//! Consensus primitives shared by the runtime, the node, miners and light
+//! clients: the Yespower-R16 seal, the Proof-of-History sequence, the FlyClient
+//! MMR and the FlyClient proof verifier.
+//!
+//! Nothing here depends on FRAME or calls into the host, so the crate builds
+//! for `no_std` and for plain wasm, as used by the CLI wallet and by browser
+//! and mobile wallets. The `Primitives` CI workflow builds it for
+//! `wasm32-unknown-unknown` without `std` to keep it that way.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod flyclient;
+pub mod mmr;
+pub mod poh;
+pub mod yespower;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,147 @@
+//! Merkle Mountain Range (MMR) over block hashes and difficulty, for FlyClient.
+//!
+//! Leaf `i` commits to the hash of block `i` and the difficulty it was mined
+//! at. Every inner node commits to its two children and to the sum of their
+//! difficulty, so each node carries the cumulative difficulty of the blocks
+//! below it and a light client can sample blocks weighted by work:
+//!
+//! ```text
+//! leaf = sha256(0x00 ++ block_hash ++ difficulty)
+//! node = sha256(0x01 ++ left.hash ++ right.hash ++ left.difficulty + right.difficulty)
+//! ```
+//!
+//! Only the peaks are needed to append a leaf or compute the root, so the
+//! runtime keeps O(log n) state. The root bags the peaks from right to left
+//! with the same merge, so its difficulty is the total difficulty of the chain.
+//! Block `n` appends its parent and commits to the resulting root in a
+//! `Consensus` digest under [`MMR_ENGINE_ID`].
+
+use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sha2::{Digest as _, Sha256};
+use sp_core::{H256, U256};
+use sp_runtime::{ConsensusEngineId, Digest, DigestItem, RuntimeDebug};
+use sp_std::prelude::*;
+
+/// Engine ID of the digest carrying the MMR root a block commits to.
+pub const MMR_ENGINE_ID: ConsensusEngineId = *b"CMMR";
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// Node of the MMR.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct MmrNode {
+    /// Hash committing to the node's subtree.
+    pub hash: H256,
+    /// Total difficulty of the blocks in the node's subtree.
+    pub difficulty: U256,
+}
+
+impl MmrNode {
+    /// Leaf for a block mined at `difficulty`.
+    pub fn leaf(block_hash: H256, difficulty: U256) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update([LEAF_PREFIX]);
+        hasher.update(block_hash.as_bytes());
+        hasher.update(difficulty.encode());
+
+        Self {
+            hash: H256::from_slice(&hasher.finalize()),
+            difficulty,
+        }
+    }
+
+    /// Parent of `left` and `right`.
+    pub fn merge(left: &Self, right: &Self) -> Self {
+        let difficulty = left.difficulty.saturating_add(right.difficulty);
+
+        let mut hasher = Sha256::new();
+        hasher.update([NODE_PREFIX]);
+        hasher.update(left.hash.as_bytes());
+        hasher.update(right.hash.as_bytes());
+        hasher.update(difficulty.encode());
+
+        Self {
+            hash: H256::from_slice(&hasher.finalize()),
+            difficulty,
+        }
+    }
+}
+
+/// Peaks of an MMR, enough to append leaves and compute the root.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug, TypeInfo)]
+pub struct MerkleMountainRange {
+    /// Number of leaves appended so far.
+    pub leaf_count: u64,
+    /// Roots of the perfect subtrees, tallest (leftmost) first. Peak `i` has a
+    /// height given by the `i`-th set bit of `leaf_count`, from the top.
+    pub peaks: Vec<MmrNode>,
+}
+
+impl MerkleMountainRange {
+    /// Append a leaf, merging the peaks it completes.
+    pub fn push(&mut self, leaf: MmrNode) {
+        let mut node = leaf;
+        let mut height = 0;
+        while (self.leaf_count >> height) & 1 == 1 {
+            let left = self
+                .peaks
+                .pop()
+                .expect("a set bit in leaf_count always has a peak; qed");
+            node = MmrNode::merge(&left, &node);
+            height += 1;
+        }
+
+        self.peaks.push(node);
+        self.leaf_count = self.leaf_count.saturating_add(1);
+    }
+
+    /// Root of the range, if it has any leaves.
+    pub fn root(&self) -> Option<MmrNode> {
+        bag_peaks(&self.peaks)
+    }
+
+    /// Commitment to the current root, as carried in a header.
+    pub fn commitment(&self) -> Option<MmrCommitment> {
+        self.root().map(|root| MmrCommitment {
+            leaf_count: self.leaf_count,
+            root,
+        })
+    }
+}
+
+/// Fold `peaks` from right to left into a single root.
+pub fn bag_peaks(peaks: &[MmrNode]) -> Option<MmrNode> {
+    peaks
+        .iter()
+        .rev()
+        .copied()
+        .reduce(|right, left| MmrNode::merge(&left, &right))
+}
+
+/// MMR root committed to by a header, covering all blocks before it.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct MmrCommitment {
+    /// Number of leaves, which is the number of the committing block.
+    pub leaf_count: u64,
+    /// Root of the range. Its difficulty is the chain's total difficulty.
+    pub root: MmrNode,
+}
+
+impl MmrCommitment {
+    /// Digest item carrying this commitment in a header.
+    pub fn to_digest_item(&self) -> DigestItem {
+        DigestItem::Consensus(MMR_ENGINE_ID, self.encode())
+    }
+
+    /// Commitment carried in `digest`, if any.
+    pub fn find(digest: &Digest) -> Option<Self> {
+        digest.logs.iter().find_map(|item| match item {
+            DigestItem::Consensus(id, data) if *id == MMR_ENGINE_ID => {
+                Self::decode(&mut &data[..]).ok()
+            }
+            _ => None,
+        })
+    }
+}
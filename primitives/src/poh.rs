@@ -0,0 +1,119 @@
+//! Proof-of-History (PoH) sequence linking every block to its parent.
+//!
+//! The sequence of a block starts at its parent's hash and advances one tick
+//! per SHA-256 of the previous value:
+//!
+//! ```text
+//! h(0) = parent_hash
+//! h(i + 1) = sha256(h(i))
+//! ```
+//!
+//! The block producer keeps ticking from the moment the parent is known until
+//! it builds the block, and records the tick count and the last hash in a
+//! `Consensus` digest under [`POH_ENGINE_ID`]. Ticks cannot be computed in
+//! parallel, so a header proves that `ticks` sequential hashes were run after
+//! its parent existed. Checking a header needs nothing but the header itself,
+//! see [`verify_header`], so light clients can audit ordering claims too.
+
+use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sha2::{Digest as _, Sha256};
+use sp_core::H256;
+use sp_runtime::{traits::Header as HeaderT, ConsensusEngineId, Digest, DigestItem, RuntimeDebug};
+
+/// Engine ID of the digest carrying the PoH sequence of a block.
+pub const POH_ENGINE_ID: ConsensusEngineId = *b"CPoH";
+
+/// End of the PoH sequence of a block.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct PohDigest {
+    /// Ticks run since the parent hash.
+    pub ticks: u64,
+    /// Hash after the last tick.
+    pub hash: H256,
+}
+
+impl PohDigest {
+    /// Sequence seeded from `parent_hash`, before any tick.
+    pub fn new(parent_hash: H256) -> Self {
+        Self {
+            ticks: 0,
+            hash: parent_hash,
+        }
+    }
+
+    /// Sequence seeded from `parent_hash` after `ticks` ticks.
+    pub fn compute(parent_hash: H256, ticks: u64) -> Self {
+        let mut poh = Self::new(parent_hash);
+        poh.extend(ticks);
+        poh
+    }
+
+    /// Run `ticks` more ticks.
+    pub fn extend(&mut self, ticks: u64) {
+        self.extend_with(ticks, tick)
+    }
+
+    /// Like `extend`, running each tick with `tick`, which must compute the same
+    /// SHA-256 as [`tick`].
+    pub fn extend_with(&mut self, ticks: u64, tick: impl Fn(&H256) -> H256) {
+        for _ in 0..ticks {
+            self.hash = tick(&self.hash);
+        }
+        self.ticks = self.ticks.saturating_add(ticks);
+    }
+
+    /// Whether this is the sequence seeded from `parent_hash`, with at most `max_ticks` ticks.
+    ///
+    /// The tick count is bounded before anything is hashed, so checking costs at
+    /// most `max_ticks` hashes.
+    pub fn verify(&self, parent_hash: H256, max_ticks: u64) -> bool {
+        self.verify_with(parent_hash, max_ticks, tick)
+    }
+
+    /// Like `verify`, running each tick with `tick`, which must compute the same
+    /// SHA-256 as [`tick`].
+    pub fn verify_with(
+        &self,
+        parent_hash: H256,
+        max_ticks: u64,
+        tick: impl Fn(&H256) -> H256,
+    ) -> bool {
+        if self.ticks > max_ticks {
+            return false;
+        }
+        let mut poh = Self::new(parent_hash);
+        poh.extend_with(self.ticks, tick);
+        poh == *self
+    }
+
+    /// Digest item carrying this sequence in a header.
+    pub fn to_digest_item(&self) -> DigestItem {
+        DigestItem::Consensus(POH_ENGINE_ID, self.encode())
+    }
+
+    /// PoH sequence carried in `digest`, if any.
+    pub fn find(digest: &Digest) -> Option<Self> {
+        digest.logs.iter().find_map(|item| match item {
+            DigestItem::Consensus(id, data) if *id == POH_ENGINE_ID => {
+                Self::decode(&mut &data[..]).ok()
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Advance the sequence by one tick.
+pub fn tick(hash: &H256) -> H256 {
+    H256::from_slice(&Sha256::digest(hash.as_bytes()))
+}
+
+/// Check that `header` carries a PoH sequence seeded from its parent hash, with at
+/// most `max_ticks` ticks.
+pub fn verify_header<Header>(header: &Header, max_ticks: u64) -> bool
+where
+    Header: HeaderT<Hash = H256>,
+{
+    PohDigest::find(header.digest())
+        .map_or(false, |poh| poh.verify(*header.parent_hash(), max_ticks))
+}
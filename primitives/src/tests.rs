@@ -0,0 +1,219 @@
+use crate::{
+    flyclient::{self, FlyClientProof, VerificationError, MAX_SECURITY_PARAM},
+    mmr::{MerkleMountainRange, MmrCommitment, MmrNode},
+    poh::PohDigest,
+    yespower::{compute_work, meets_difficulty, CivicSeal, PowParams, PowSchedule},
+};
+use parity_scale_codec::Encode;
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::{H256, U256};
+use sp_runtime::{
+    generic,
+    traits::{BlakeTwo256, Header as _},
+    Digest, DigestItem,
+};
+
+type Header = generic::Header<u64, BlakeTwo256>;
+
+// Cabeçalho em `number` sobre `parent`, ainda sem selo
+fn header(number: u64, parent: H256, digest: Digest) -> Header {
+    Header::new(number, H256::zero(), H256::zero(), parent, digest)
+}
+
+// Sela `header` com o primeiro nonce cujo trabalho atinge `difficulty`, após
+// registrar uma sequência PoH vazia
+fn seal_header(mut header: Header, difficulty: U256) -> Header {
+    let poh = PohDigest::new(*header.parent_hash());
+    header.digest_mut().push(poh.to_digest_item());
+
+    let pre_hash = header.hash();
+    let seal = (0u64..)
+        .find_map(|nonce| {
+            let nonce = nonce.to_le_bytes();
+            let work = compute_work(&PowParams::default(), pre_hash.as_bytes(), &nonce);
+            meets_difficulty(&work, difficulty)
+                .then(|| CivicSeal::new(difficulty, nonce, poh.hash, work))
+        })
+        .unwrap();
+
+    header
+        .digest_mut()
+        .push(DigestItem::Seal(POW_ENGINE_ID, seal.encode()));
+    header
+}
+
+#[test]
+fn mmr_peaks_follow_leaf_count() {
+    let leaves: Vec<MmrNode> = (0..7u64)
+        .map(|n| MmrNode::leaf(H256::from_low_u64_be(n), U256::from(n)))
+        .collect();
+    let mut mmr = MerkleMountainRange::default();
+    assert_eq!(mmr.root(), None);
+    for leaf in &leaves {
+        mmr.push(*leaf);
+    }
+
+    // Com 7 folhas (0b111) há picos de altura 2, 1 e 0
+    let left = MmrNode::merge(
+        &MmrNode::merge(&leaves[0], &leaves[1]),
+        &MmrNode::merge(&leaves[2], &leaves[3]),
+    );
+    let middle = MmrNode::merge(&leaves[4], &leaves[5]);
+    assert_eq!(mmr.leaf_count, 7);
+    assert_eq!(mmr.peaks, vec![left, middle, leaves[6]]);
+
+    // A raiz junta os picos da direita para a esquerda e soma toda a dificuldade
+    let root = mmr.root().unwrap();
+    assert_eq!(
+        root,
+        MmrNode::merge(&left, &MmrNode::merge(&middle, &leaves[6]))
+    );
+    assert_eq!(root.difficulty, U256::from(21));
+
+    // Folhas e nós internos não se confundem
+    assert_ne!(
+        MmrNode::leaf(H256::zero(), U256::zero()).hash,
+        MmrNode::merge(&leaves[0], &leaves[1]).hash
+    );
+}
+
+// Nó `(last, height)` do MMR sobre `leaves`, calculado a partir das folhas
+fn mmr_node(leaves: &[MmrNode], last: u64, height: u32) -> Option<MmrNode> {
+    if height == 0 {
+        return leaves.get(last as usize).copied();
+    }
+    let left = mmr_node(leaves, last.checked_sub(1 << (height - 1))?, height - 1)?;
+    let right = mmr_node(leaves, last, height - 1)?;
+    Some(MmrNode::merge(&left, &right))
+}
+
+// Cadeia selada em que cada cabeçalho se compromete com o MMR dos anteriores
+fn sealed_chain(length: u64, difficulty: U256) -> (Vec<Header>, Vec<MmrNode>) {
+    let genesis = header(0, H256::zero(), Digest::default());
+    let mut leaves = vec![MmrNode::leaf(genesis.hash(), U256::zero())];
+    let mut headers = vec![genesis];
+    let mut mmr = MerkleMountainRange::default();
+    mmr.push(leaves[0]);
+
+    for number in 1..length {
+        let digest = Digest {
+            logs: vec![mmr.commitment().unwrap().to_digest_item()],
+        };
+        let parent = headers.last().unwrap().hash();
+        let header = seal_header(header(number, parent, digest), difficulty);
+        let leaf = MmrNode::leaf(header.hash(), difficulty);
+        mmr.push(leaf);
+        leaves.push(leaf);
+        headers.push(header);
+    }
+
+    (headers, leaves)
+}
+
+// Prova FlyClient do último cabeçalho, montada como o nó faz
+fn flyclient_proof(
+    headers: &[Header],
+    leaves: &[MmrNode],
+    security_param: u32,
+) -> FlyClientProof<Header> {
+    let head = headers.last().unwrap().clone();
+    let node = |last, height| mmr_node(leaves, last, height);
+    let total = MmrCommitment::find(head.digest()).unwrap().root.difficulty;
+
+    let samples = (0..security_param)
+        .map(|index| {
+            let point = flyclient::sample_point(&head.hash(), index, total).unwrap();
+            let leaf = flyclient::find_leaf(point, head.number, node).unwrap();
+            let proof = flyclient::prove(leaf, head.number, node).unwrap();
+            (headers[leaf as usize].clone(), proof)
+        })
+        .collect();
+
+    FlyClientProof {
+        genesis: flyclient::prove(0, head.number, node).unwrap(),
+        head,
+        samples,
+        schedule: PowSchedule::default(),
+    }
+}
+
+#[test]
+fn mmr_proofs_locate_leaves_by_difficulty() {
+    // Folha n com dificuldade n
+    let leaves: Vec<MmrNode> = (0..7u64)
+        .map(|n| MmrNode::leaf(H256::from_low_u64_be(n), U256::from(n)))
+        .collect();
+    let mut mmr = MerkleMountainRange::default();
+    for leaf in &leaves {
+        mmr.push(*leaf);
+    }
+    let commitment = mmr.commitment().unwrap();
+    let node = |last, height| mmr_node(&leaves, last, height);
+
+    // Toda folha é provada junto com a dificuldade acumulada antes dela
+    for n in 0..7u64 {
+        let proof = flyclient::prove(n, 7, node).unwrap();
+        let before = U256::from(n * n.saturating_sub(1) / 2);
+        assert_eq!(proof.verify(leaves[n as usize], &commitment), Some(before));
+
+        // A mesma prova não vale para outra folha
+        let other = leaves[((n + 1) % 7) as usize];
+        assert_eq!(proof.verify(other, &commitment), None);
+    }
+
+    // O ponto 10 cai na folha 5, que cobre a dificuldade de 10 a 14
+    assert_eq!(flyclient::find_leaf(U256::from(9), 7, node), Some(4));
+    assert_eq!(flyclient::find_leaf(U256::from(10), 7, node), Some(5));
+    assert_eq!(flyclient::find_leaf(U256::from(14), 7, node), Some(5));
+    assert_eq!(flyclient::find_leaf(U256::from(21), 7, node), None);
+}
+
+#[test]
+fn flyclient_proof_is_verified_from_genesis() {
+    let difficulty = U256::from(4);
+    let (headers, leaves) = sealed_chain(9, difficulty);
+    let genesis = headers[0].hash();
+    let proof = flyclient_proof(&headers, &leaves, 4);
+
+    // A prova confirma o bloco mais recente e toda a dificuldade da cadeia
+    let head = flyclient::verify(&proof, genesis, 4).unwrap();
+    assert_eq!(head.hash, headers[8].hash());
+    assert_eq!(head.number, 8);
+    assert_eq!(head.total_difficulty, difficulty * 8);
+
+    // Outro bloco gênese
+    assert_eq!(
+        flyclient::verify(&proof, H256::repeat_byte(1), 4),
+        Err(VerificationError::GenesisMismatch)
+    );
+
+    // A prova tem menos amostras que o pedido
+    assert_eq!(
+        flyclient::verify(&proof, genesis, 5),
+        Err(VerificationError::WrongSampleCount)
+    );
+    assert_eq!(
+        flyclient::verify(&proof, genesis, MAX_SECURITY_PARAM + 1),
+        Err(VerificationError::SecurityParamTooHigh)
+    );
+
+    // O provador não escolhe os blocos amostrados
+    let mut swapped = proof.clone();
+    let leaf = swapped.samples[0].1.leaf_index % 7 + 1;
+    swapped.samples[0] = (
+        headers[leaf as usize].clone(),
+        flyclient::prove(leaf, 8, |last, height| mmr_node(&leaves, last, height)).unwrap(),
+    );
+    assert_eq!(
+        flyclient::verify(&swapped, genesis, 4),
+        Err(VerificationError::WrongSample)
+    );
+
+    // Cabeçalhos alterados perdem o selo
+    let mut forged = proof;
+    forged.samples[0].0.state_root = H256::repeat_byte(1);
+    assert_eq!(
+        flyclient::verify(&forged, genesis, 4),
+        Err(VerificationError::InvalidSeal)
+    );
+}
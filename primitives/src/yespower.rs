@@ -0,0 +1,155 @@
+//! Yespower-R16 work function and seal format shared by the runtime, the node
+//! and external miners.
+//!
+//! Both the pallet and the client-side `PowAlgorithm` hash the block pre-hash
+//! followed by the miner's nonce, so the helpers here are kept free of any
+//! pallet state and usable from `no_std`.
+//!
+//! The work function takes [`PowParams`], which governance may change at a
+//! scheduled height (see [`PowSchedule`]). The `yesha256` library only
+//! implements Yespower-R16, so N = 4096 and r = 16 are fixed; what can change
+//! is a personalization string hashed in ahead of the pre-hash. A new string
+//! leaves the cost of a hash unchanged but breaks any miner built around the
+//! previous input.
+
+use crate::poh::PohDigest;
+use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sp_core::{H256, U256};
+use sp_runtime::{Digest, RuntimeDebug};
+use sp_std::prelude::*;
+use yesha256::yespower_r16;
+
+/// Version of the seal format produced by this code.
+pub const SEAL_VERSION: u8 = 1;
+
+/// Width of the miner's nonce. The built-in miner counts with a `u64`, and
+/// Stratum splits it into a 4-byte `extranonce1` and a 4-byte `extranonce2`.
+pub const NONCE_SIZE: usize = 8;
+
+/// Nonce chosen by the miner.
+pub type Nonce = [u8; NONCE_SIZE];
+
+/// Longest personalization string [`PowParams`] may carry.
+pub const MAX_PERSONALIZATION_LEN: usize = 32;
+
+/// Parameters of the work function.
+///
+/// The default, with an empty personalization, is the plain Yespower-R16 the
+/// chain started with.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct PowParams {
+    /// Bytes hashed ahead of the pre-hash and nonce.
+    pub personalization: Vec<u8>,
+}
+
+impl PowParams {
+    /// Whether the parameters can be scheduled.
+    pub fn is_valid(&self) -> bool {
+        self.personalization.len() <= MAX_PERSONALIZATION_LEN
+    }
+}
+
+/// Work function parameters by activation height, lowest height first.
+///
+/// Blocks below the first activation height use [`PowParams::default`].
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct PowSchedule(pub Vec<(u64, PowParams)>);
+
+impl PowSchedule {
+    /// Parameters block `number` is mined with.
+    pub fn params_at(&self, number: u64) -> PowParams {
+        self.0
+            .iter()
+            .rev()
+            .find(|(activation, _)| *activation <= number)
+            .map(|(_, params)| params.clone())
+            .unwrap_or_default()
+    }
+
+    /// Switch to `params` at block `activation`, replacing whatever was
+    /// scheduled at that height.
+    pub fn insert(&mut self, activation: u64, params: PowParams) {
+        match self.0.binary_search_by_key(&activation, |(at, _)| *at) {
+            Ok(index) => self.0[index].1 = params,
+            Err(index) => self.0.insert(index, (activation, params)),
+        }
+    }
+}
+
+/// Seal attached to a mined block.
+///
+/// The version comes first in the SCALE encoding, so later formats can be told
+/// apart from this one. Every field has a fixed width: a seal always encodes to
+/// [`CivicSeal::ENCODED_SIZE`] bytes, and [`CivicSeal::decode_raw`] rejects
+/// anything else before decoding it.
+#[derive(Encode, Decode, MaxEncodedLen, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct CivicSeal {
+    /// Seal format version, [`SEAL_VERSION`].
+    pub version: u8,
+    /// Difficulty the block was mined at.
+    pub difficulty: U256,
+    /// Nonce chosen by the miner.
+    pub nonce: Nonce,
+    /// Hash at the end of the block's PoH sequence.
+    pub poh: H256,
+    /// Yespower-R16 hash of the personalization, pre-hash and nonce.
+    pub work: H256,
+}
+
+impl CivicSeal {
+    /// Size of an encoded seal.
+    pub const ENCODED_SIZE: usize = 1 + 32 + NONCE_SIZE + 32 + 32;
+
+    /// Seal of the current version.
+    pub fn new(difficulty: U256, nonce: Nonce, poh: H256, work: H256) -> Self {
+        Self {
+            version: SEAL_VERSION,
+            difficulty,
+            nonce,
+            poh,
+            work,
+        }
+    }
+
+    /// Decode a seal as carried in a header. Returns `None` for any other size
+    /// or for a version this code does not know.
+    pub fn decode_raw(raw: &[u8]) -> Option<Self> {
+        if raw.len() != Self::ENCODED_SIZE {
+            return None;
+        }
+
+        Self::decode(&mut &raw[..])
+            .ok()
+            .filter(|seal| seal.version == SEAL_VERSION)
+    }
+
+    /// Check that `work` is the hash of `pre_hash` and `nonce` under `params`
+    /// and meets `difficulty`.
+    pub fn verify(&self, params: &PowParams, pre_hash: &[u8]) -> bool {
+        let work = compute_work(params, pre_hash, &self.nonce);
+        work == self.work && meets_difficulty(&work, self.difficulty)
+    }
+
+    /// Whether `digest` carries the PoH sequence this seal refers to.
+    pub fn follows_poh(&self, digest: &Digest) -> bool {
+        PohDigest::find(digest).map_or(false, |poh| poh.hash == self.poh)
+    }
+}
+
+/// Hash `personalization ++ pre_hash ++ nonce` with Yespower-R16.
+pub fn compute_work(params: &PowParams, pre_hash: &[u8], nonce: &[u8]) -> H256 {
+    let personalization = &params.personalization;
+    let mut input = Vec::with_capacity(personalization.len() + pre_hash.len() + nonce.len());
+    input.extend_from_slice(personalization);
+    input.extend_from_slice(pre_hash);
+    input.extend_from_slice(nonce);
+    H256::from(yespower_r16(&input))
+}
+
+/// Whether `work` satisfies `difficulty`, i.e. `work * difficulty` does not overflow 256 bits.
+pub fn meets_difficulty(work: &H256, difficulty: U256) -> bool {
+    let work = U256::from(&work[..]);
+    let (_, overflowed) = work.overflowing_mul(difficulty);
+    !overflowed
+}
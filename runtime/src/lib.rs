@@ -195,7 +195,7 @@ parameter_types! {
 impl pallet_timestamp::Config for Runtime {
     /// A timestamp: milliseconds since the unix epoch.
     type Moment = u64;
-    type OnTimestampSet = ();
+    type OnTimestampSet = PowPallet;
     type MinimumPeriod = MinimumPeriod;
     type WeightInfo = ();
 }
@@ -229,7 +229,7 @@ parameter_types! {
 
 impl pallet_transaction_payment::Config for Runtime {
     type RuntimeEvent = RuntimeEvent;
-    type OnChargeTransaction = CurrencyAdapter<Balances, ()>;
+    type OnChargeTransaction = CurrencyAdapter<Balances, civicchain_pow::FeesToAuthor<Runtime>>;
     type OperationalFeeMultiplier = ConstU8<5>;
     type WeightToFee = IdentityFee<Balance>;
     type LengthToFee = IdentityFee<Balance>;
@@ -247,16 +247,66 @@ parameter_types! {
     pub const BlocksPerYear: u32 = 1_576_800; // ~200 seconds (3.33 min) per block, 365 days
     pub const HalvingYears: u32 = 5;
     pub const MaxSupply: Balance = 29_000_000 * 10_u128.pow(18); // 29 million CVX
+    pub const DifficultyWindow: u32 = 60; // LWMA window, ~3.3 hours
+    pub const TargetBlockTime: u64 = 200_000; // 200 seconds in milliseconds
+    pub const DifficultyClampFactor: u32 = 2;
+    pub const MedianTimeSpan: u32 = 11;
+    pub const MaxUncleDepth: u32 = 6; // Uncles may branch off up to ~20 minutes back
+    pub const MaxUncles: u32 = 2;
+    pub MaxPohTicks: u64 = max_poh_ticks(); // Checked within 10% of a block
+    pub const MinPowParamsDelay: u32 = 6_048; // ~2 weeks for miners to upgrade
+    pub const MinProposalStake: Balance = 1_000 * 10_u128.pow(18); // 1000 CVX
+    pub const ProposalDeposit: Balance = 100 * 10_u128.pow(18); // 100 CVX, per proposal
+    pub const MaxActiveProposals: u32 = 100;
+    pub const MaxProposalsPerAccount: u32 = 5;
+    pub const MaxDelegationDepth: u32 = 10;
+    pub const StakeLockPeriod: u32 = 4_320; // ~10 days after voting ends
+    pub const MaxVotingPeriod: u32 = 6_048; // ~2 weeks
+    pub const Quorum: Perbill = Perbill::from_percent(5); // Of all CVX issued, genesis balances included
+}
+
+/// Share of a block's compute that checking its PoH sequence may take.
+const POH_BLOCK_SHARE: Perbill = Perbill::from_percent(10);
+
+/// Ticks `set_poh` can check within `POH_BLOCK_SHARE` of a block, at the
+/// benchmarked cost of a tick.
+fn max_poh_ticks() -> u64 {
+    use civicchain_pow::WeightInfo;
+    type PowWeights = civicchain_pow::weights::SubstrateWeight<Runtime>;
+
+    let budget = POH_BLOCK_SHARE * BlockWeights::get().max_block.ref_time();
+    let base = PowWeights::set_poh(0).ref_time();
+    let per_tick = PowWeights::set_poh(1).ref_time().saturating_sub(base).max(1);
+    budget.saturating_sub(base) / per_tick
 }
 
 impl civicchain_pow::Config for Runtime {
     type RuntimeEvent = RuntimeEvent;
     type Currency = Balances;
-    type RewardHandler = ();
+    type FindAuthor = civicchain_pow::PowDigestAuthor<AccountId>;
     type BlockReward = BlockReward;
     type BlocksPerYear = BlocksPerYear;
     type HalvingYears = HalvingYears;
     type MaxSupply = MaxSupply;
+    type Time = Timestamp;
+    type DifficultyWindow = DifficultyWindow;
+    type TargetBlockTime = TargetBlockTime;
+    type DifficultyClampFactor = DifficultyClampFactor;
+    type MedianTimeSpan = MedianTimeSpan;
+    type MaxUncleDepth = MaxUncleDepth;
+    type MaxUncles = MaxUncles;
+    type MaxPohTicks = MaxPohTicks;
+    type MinPowParamsDelay = MinPowParamsDelay;
+    type MinProposalStake = MinProposalStake;
+    type ProposalDeposit = ProposalDeposit;
+    type MaxActiveProposals = MaxActiveProposals;
+    type MaxProposalsPerAccount = MaxProposalsPerAccount;
+    type MaxDelegationDepth = MaxDelegationDepth;
+    type StakeLockPeriod = StakeLockPeriod;
+    type MaxVotingPeriod = MaxVotingPeriod;
+    type Quorum = Quorum;
+    type ApprovalThreshold = civicchain_pow::DefaultApprovalThresholds;
+    type WeightInfo = civicchain_pow::weights::SubstrateWeight<Runtime>;
 }
 
 parameter_types! {
@@ -377,6 +427,7 @@ mod benches {
         [pallet_balances, Balances]
         [pallet_timestamp, Timestamp]
         [pallet_contracts, Contracts]
+        [civicchain_pow, PowPallet]
     );
 }
 
@@ -452,6 +503,28 @@ impl_runtime_apis! {
         }
     }
 
+    impl civicchain_pow::CivicPowApi<Block, AccountId, Balance> for Runtime {
+        fn author() -> Option<AccountId> {
+            PowPallet::author()
+        }
+
+        fn remaining_emission() -> Balance {
+            PowPallet::remaining_emission()
+        }
+
+        fn pow_params() -> civicchain_pow::PowParams {
+            PowPallet::pow_params()
+        }
+
+        fn pow_schedule() -> civicchain_pow::PowSchedule {
+            PowPallet::pow_schedule()
+        }
+
+        fn target_block_time() -> u64 {
+            PowPallet::target_block_time()
+        }
+    }
+
     impl sp_session::SessionKeys<Block> for Runtime {
         fn generate_session_keys(_seed: Option<Vec<u8>>) -> Vec<u8> {
             Vec::new()